@@ -1,16 +1,15 @@
-use std::io;
 use std::ops::Deref;
 use std::time::Duration;
 
+use async_trait::async_trait;
+use connman::api::agent::{Agent, AgentError, InputRequest, InputResponse};
+use connman::api::service_id::ServiceId;
 use connman::api::Error as ConnmanError;
 use connman::{Manager, Technology};
 use dbus::nonblock::NonblockReply;
 use dbus_tokio::connection;
 use structopt::StructOpt;
 
-// TODO: Is there a way to determine this path from connman?
-const WIFI_SERVICE_CONFIG_FILE: &str = "/usr/local/var/lib/connman/wifi.config";
-
 #[derive(Debug, StructOpt)]
 #[structopt(about = "Connects to a SSID with ConnMan over D-Bus")]
 struct WifiConnectOpts {
@@ -40,66 +39,32 @@ pub async fn get_technology_wifi<T: NonblockReply, C: Deref<Target = T> + Clone>
         })
 }
 
-#[rustfmt::skip]
-pub fn generate_wifi_config(ssid: &str, password: Option<&str>) -> String {
-    let hex_ssid = hex::encode(ssid).to_uppercase();
-    if let Some(pass) = password {
-        format!("[global]
-Name = Wi-Fi
-Description = Wi-Fi configuration
-
-[service_wifi]
-Type = wifi
-SSID = {}
-Security = psk
-IPv4 = 192.168.1.2/255.255.255.0/192.168.1.1
-IPv6=off
-Passphrase = {}
-Hidden = false", hex_ssid, pass)
-    } else {
-        format!("[global]
-Name = Wi-Fi
-Description = Wi-Fi configuration
-
-[service_wifi]
-Type = wifi
-SSID = {}
-Security = open
-IPv4 = 192.168.1.2/255.255.255.0/192.168.1.1
-IPv6=off
-Hidden = false", hex_ssid)
-    }
+/// Agent that answers passphrase requests with the one given on the command line.
+struct PassphraseAgent {
+    passphrase: Option<String>,
 }
 
-pub fn write_wifi_service_config(s: &str) -> Result<(), io::Error> {
-    use std::fs::File;
-    use std::io::Write;
-    use std::path::Path;
-
-    let path = Path::new(WIFI_SERVICE_CONFIG_FILE);
-
-    let mut file = File::create(&path)?;
-
-    file.write_all(s.as_bytes())
+#[async_trait]
+impl Agent for PassphraseAgent {
+    async fn request_input(
+        &self,
+        _service: dbus::Path<'static>,
+        request: InputRequest,
+    ) -> Result<InputResponse, AgentError> {
+        match (&request.passphrase, &self.passphrase) {
+            (Some(_), Some(passphrase)) => Ok(InputResponse {
+                passphrase: Some(passphrase.clone()),
+                ..Default::default()
+            }),
+            _ => Err(AgentError::Canceled),
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
     let args = WifiConnectOpts::from_args();
 
-    if !args.disconnect {
-        let prov = generate_wifi_config(
-            args.ssid.as_str(),
-            args.password.as_ref().map(|s| s.as_str()),
-        );
-
-        write_wifi_service_config(prov.as_str()).expect("Failed to write wifi service config");
-
-        println!("{}", prov);
-    }
-
-    let hex_ssid = hex::encode(&args.ssid);
-
     let (resource, conn) = connection::new_system_sync().unwrap();
     tokio::spawn(async {
         let err = resource.await;
@@ -108,6 +73,15 @@ async fn main() {
 
     let manager = Manager::new(conn, Duration::from_secs(10));
 
+    // Answers connman's passphrase request while connecting
+    let agent = PassphraseAgent {
+        passphrase: args.password.clone(),
+    };
+    let _agent = manager
+        .register_agent("/net/connman/example/agent".into(), agent)
+        .await
+        .unwrap();
+
     let wifi = get_technology_wifi(&manager).await.unwrap();
 
     // Initiate scan
@@ -115,30 +89,20 @@ async fn main() {
 
     // List services once scan completes
     let services = manager.clone().get_services().await.unwrap();
-    let maybe_svc = services.iter().find(|svc| {
-        //wifi_ffffffffffff_00112233aabbccdd_managed_psk
-        //tech_mac.addr...._hex.ssid........_security...
-        let pathv = svc
-            .path()
-            .as_cstr()
-            .to_str()
-            .unwrap()
-            .split("_")
-            .collect::<Vec<&str>>();
-        let svc_hex_ssid = *pathv.get(2).unwrap();
-        let found = svc_hex_ssid == hex_ssid;
-        if found {
+    let maybe_svc = services.iter().find(|svc| match svc.id() {
+        Ok(ServiceId::Wifi { ref ssid, .. }) if ssid.as_slice() == args.ssid.as_bytes() => {
             println!("Found service: {:?}", svc.path());
-        } else {
-            let svc_ssid_str = hex::decode(&svc_hex_ssid)
-                .map(|s| String::from_utf8(s).expect("Failed to turn ssid into string"));
-            if let Ok(ssid) = svc_ssid_str {
-                println!("{} != {}", ssid, args.ssid);
-            } else {
-                println!("Failed to decode hex string: {}", svc_hex_ssid);
-            }
+            true
+        }
+        Ok(ServiceId::Wifi { ssid, .. }) => {
+            println!("{} != {}", String::from_utf8_lossy(&ssid), args.ssid);
+            false
+        }
+        Ok(_) => false,
+        Err(e) => {
+            println!("Failed to parse service path {:?}: {}", svc.path(), e);
+            false
         }
-        found
     });
 
     if let Some(svc) = maybe_svc {
@@ -0,0 +1,491 @@
+//! Server side of the connman Agent interface (`net.connman.Agent`)
+
+use async_trait::async_trait;
+use dbus::arg::{RefArg, Variant};
+use dbus::nonblock::{Proxy, SyncConnection};
+use dbus::Message;
+use thiserror::Error;
+
+use super::export::{call_no_reply, error_reply, invalid_args, Exported};
+use super::gen::manager::Manager as IManager;
+use super::retry::RetryPolicy;
+use super::{get_property_argiter, Error as ApiError, FromProperties, PropertyError, RefArgMap};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+const INTERFACE: &str = "net.connman.Agent";
+
+/// Errors an `Agent` can reply with, as defined by the connman agent API.
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum AgentError {
+    /// Ask connman to retry connecting (`ReportError`, `ReportPeerError`)
+    #[error("Retry")]
+    Retry,
+    /// The user canceled the request
+    #[error("Canceled")]
+    Canceled,
+    /// The user rejected a peer authorization request
+    #[error("Rejected")]
+    Rejected,
+    /// Ask connman to hand login over to a browser (`RequestInput`)
+    #[error("LaunchBrowser")]
+    LaunchBrowser,
+}
+
+impl From<AgentError> for &'static str {
+    fn from(err: AgentError) -> Self {
+        match err {
+            AgentError::Retry => "net.connman.Agent.Error.Retry",
+            AgentError::Canceled => "net.connman.Agent.Error.Canceled",
+            AgentError::Rejected => "net.connman.Agent.Error.Rejected",
+            AgentError::LaunchBrowser => "net.connman.Agent.Error.LaunchBrowser",
+        }
+    }
+}
+
+/// Agent that connman calls back into for credentials and error handling.
+///
+/// Every method has a default implementation that declines the request, so
+/// only the calls of interest need to be implemented.
+#[async_trait]
+pub trait Agent: Send + Sync + 'static {
+    /// Called when connman unregisters the agent, e.g. on shutdown.
+    async fn release(&self) {}
+
+    /// Called when connecting to `service` failed with `error`.
+    async fn report_error(
+        &self,
+        service: dbus::Path<'static>,
+        error: String,
+    ) -> Result<(), AgentError> {
+        Ok(())
+    }
+
+    /// Called when `service` requires a web login at `url`.
+    async fn request_browser(
+        &self,
+        service: dbus::Path<'static>,
+        url: String,
+    ) -> Result<(), AgentError> {
+        Err(AgentError::Canceled)
+    }
+
+    /// Called when connecting to `service` requires credentials.
+    async fn request_input(
+        &self,
+        service: dbus::Path<'static>,
+        request: InputRequest,
+    ) -> Result<InputResponse, AgentError> {
+        Err(AgentError::Canceled)
+    }
+
+    /// Called when connecting to `peer` failed with `error`.
+    async fn report_peer_error(
+        &self,
+        peer: dbus::Path<'static>,
+        error: String,
+    ) -> Result<(), AgentError> {
+        Ok(())
+    }
+
+    /// Called when connecting to `peer` requires authorization.
+    async fn request_peer_authorization(
+        &self,
+        peer: dbus::Path<'static>,
+        request: InputRequest,
+    ) -> Result<InputResponse, AgentError> {
+        Err(AgentError::Rejected)
+    }
+
+    /// Called when connman cancels a pending request.
+    async fn cancel(&self) {}
+}
+
+/// Agent exported on the connection and registered with the connman Manager.
+///
+/// The agent is unregistered when this is dropped; use `unregister` to wait
+/// for connman to acknowledge it instead.
+pub struct AgentRegistration {
+    path: dbus::Path<'static>,
+    manager: Option<Proxy<'static, Arc<SyncConnection>>>,
+    _exported: Exported,
+}
+
+impl AgentRegistration {
+    pub(crate) async fn new<A: Agent>(
+        manager: Proxy<'static, Arc<SyncConnection>>,
+        path: dbus::Path<'static>,
+        agent: A,
+        retry: &RetryPolicy,
+    ) -> Result<Self, ApiError> {
+        let agent = Arc::new(agent);
+        let exported = Exported::new(&manager.connection, path.clone(), INTERFACE, move |msg| {
+            Box::pin(dispatch(agent.clone(), msg))
+        });
+
+        retry
+            .run(|| IManager::register_agent(&manager, path.clone()))
+            .await?;
+
+        Ok(AgentRegistration {
+            path,
+            manager: Some(manager),
+            _exported: exported,
+        })
+    }
+
+    pub fn path(&self) -> &dbus::Path<'static> {
+        &self.path
+    }
+
+    pub async fn unregister(mut self) -> Result<(), ApiError> {
+        match self.manager.take() {
+            Some(manager) => Ok(IManager::unregister_agent(&manager, self.path.clone()).await?),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for AgentRegistration {
+    fn drop(&mut self) {
+        if let Some(manager) = self.manager.take() {
+            call_no_reply(
+                &manager.connection,
+                "net.connman",
+                "/".into(),
+                "net.connman.Manager",
+                "UnregisterAgent",
+                (self.path.clone(),),
+            );
+        }
+    }
+}
+
+fn unit_reply(msg: &Message, res: Result<(), AgentError>) -> Message {
+    match res {
+        Ok(()) => msg.method_return(),
+        Err(e) => error_reply(msg, e.clone().into(), &e.to_string()),
+    }
+}
+
+fn input_reply(msg: &Message, res: Result<InputResponse, AgentError>) -> Message {
+    match res {
+        Ok(response) => msg.method_return().append1(response.into_dict()),
+        Err(e) => error_reply(msg, e.clone().into(), &e.to_string()),
+    }
+}
+
+fn read_input_request(msg: &Message) -> Result<(dbus::Path<'static>, InputRequest), Message> {
+    let (path, fields) = msg
+        .read2::<dbus::Path, RefArgMap>()
+        .map_err(|_| invalid_args(msg))?;
+    let request = InputRequest::try_from(fields).map_err(|e| {
+        error_reply(
+            msg,
+            "org.freedesktop.DBus.Error.InvalidArgs",
+            &e.to_string(),
+        )
+    })?;
+    Ok((path.into_static(), request))
+}
+
+async fn dispatch<A: Agent>(agent: Arc<A>, msg: Message) -> Message {
+    let member = msg.member().map(|m| m.to_string()).unwrap_or_default();
+    match member.as_str() {
+        "Release" => {
+            agent.release().await;
+            msg.method_return()
+        }
+        "ReportError" => match msg.read2::<dbus::Path, String>() {
+            Ok((service, error)) => {
+                let service = service.into_static();
+                let res = agent.report_error(service, error).await;
+                unit_reply(&msg, res)
+            }
+            Err(_) => invalid_args(&msg),
+        },
+        "RequestBrowser" => match msg.read2::<dbus::Path, String>() {
+            Ok((service, url)) => {
+                let service = service.into_static();
+                let res = agent.request_browser(service, url).await;
+                unit_reply(&msg, res)
+            }
+            Err(_) => invalid_args(&msg),
+        },
+        "RequestInput" => match read_input_request(&msg) {
+            Ok((service, request)) => {
+                let res = agent.request_input(service, request).await;
+                input_reply(&msg, res)
+            }
+            Err(reply) => reply,
+        },
+        "ReportPeerError" => match msg.read2::<dbus::Path, String>() {
+            Ok((peer, error)) => {
+                let peer = peer.into_static();
+                let res = agent.report_peer_error(peer, error).await;
+                unit_reply(&msg, res)
+            }
+            Err(_) => invalid_args(&msg),
+        },
+        "RequestPeerAuthorization" => match read_input_request(&msg) {
+            Ok((peer, request)) => {
+                let res = agent.request_peer_authorization(peer, request).await;
+                input_reply(&msg, res)
+            }
+            Err(reply) => reply,
+        },
+        "Cancel" => {
+            agent.cancel().await;
+            msg.method_return()
+        }
+        _ => error_reply(
+            &msg,
+            "org.freedesktop.DBus.Error.UnknownMethod",
+            &format!("Unknown method '{}'", member),
+        ),
+    }
+}
+
+/// Fields connman asks for in `RequestInput` and `RequestPeerAuthorization`.
+///
+/// Only the fields present in the request are set.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct InputRequest {
+    /// Network name, for hidden networks
+    pub name: Option<InputField>,
+    /// Network SSID as raw bytes, alternate to `name`
+    pub ssid: Option<InputField>,
+    /// Identity (username) for EAP authentication
+    pub identity: Option<InputField>,
+    /// Passphrase; `InputField::type_` tells which kind of key is expected
+    pub passphrase: Option<InputField>,
+    /// Passphrase that was used before, if it failed; informational only
+    pub previous_passphrase: Option<InputField>,
+    /// WPS pin, or an empty string for push-button mode
+    pub wps: Option<InputField>,
+    /// Username for WISPr authentication
+    pub username: Option<InputField>,
+    /// Password for WISPr authentication
+    pub password: Option<InputField>,
+}
+
+impl InputRequest {
+    pub fn try_from(fields: RefArgMap) -> Result<Self, PropertyError> {
+        Ok(InputRequest {
+            name: FromProperties::from_properties(&fields, InputKind::Name.into())?,
+            ssid: FromProperties::from_properties(&fields, InputKind::Ssid.into())?,
+            identity: FromProperties::from_properties(&fields, InputKind::Identity.into())?,
+            passphrase: FromProperties::from_properties(&fields, InputKind::Passphrase.into())?,
+            previous_passphrase: FromProperties::from_properties(
+                &fields,
+                InputKind::PreviousPassphrase.into(),
+            )?,
+            wps: FromProperties::from_properties(&fields, InputKind::Wps.into())?,
+            username: FromProperties::from_properties(&fields, InputKind::Username.into())?,
+            password: FromProperties::from_properties(&fields, InputKind::Password.into())?,
+        })
+    }
+}
+
+/// Values to reply to an `InputRequest` with.
+///
+/// Unset fields are left out of the reply.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct InputResponse {
+    pub name: Option<String>,
+    pub ssid: Option<Vec<u8>>,
+    pub identity: Option<String>,
+    pub passphrase: Option<String>,
+    pub wps: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl InputResponse {
+    fn into_dict(self) -> RefArgMap {
+        let mut dict = RefArgMap::new();
+        let mut insert = |kind: InputKind, value: Option<Box<dyn RefArg>>| {
+            if let Some(value) = value {
+                let key: &'static str = kind.into();
+                dict.insert(key.to_string(), Variant(value));
+            }
+        };
+
+        insert(InputKind::Name, self.name.map(|v| Box::new(v) as _));
+        insert(InputKind::Ssid, self.ssid.map(|v| Box::new(v) as _));
+        insert(InputKind::Identity, self.identity.map(|v| Box::new(v) as _));
+        insert(
+            InputKind::Passphrase,
+            self.passphrase.map(|v| Box::new(v) as _),
+        );
+        insert(InputKind::Wps, self.wps.map(|v| Box::new(v) as _));
+        insert(InputKind::Username, self.username.map(|v| Box::new(v) as _));
+        insert(InputKind::Password, self.password.map(|v| Box::new(v) as _));
+        dict
+    }
+}
+
+/// Description of a single requested input field.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InputField {
+    pub type_: InputType,
+    pub requirement: Requirement,
+    /// Other fields that can be given instead of this one
+    pub alternates: Vec<String>,
+    /// Current value, for informational fields
+    pub value: Option<String>,
+}
+
+impl FromProperties for InputField {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        let mut i = get_property_argiter(properties, prop_name)?;
+        let mut m: HashMap<&str, &dyn RefArg> = HashMap::new();
+        while let Some(key) = i.next().and_then(|k| k.as_str()) {
+            if let Some(val) = i.next() {
+                let _ = m.insert(key, val);
+            }
+        }
+
+        let type_ = m
+            .get(InputFieldKind::Type.into())
+            .and_then(|refarg| refarg.as_str())
+            .ok_or(PropertyError::NotPresent(Cow::Borrowed(prop_name)))?
+            .parse::<InputType>()?;
+
+        let requirement = m
+            .get(InputFieldKind::Requirement.into())
+            .and_then(|refarg| refarg.as_str())
+            .ok_or(PropertyError::NotPresent(Cow::Borrowed(prop_name)))?
+            .parse::<Requirement>()?;
+
+        let alternates = m
+            .get(InputFieldKind::Alternates.into())
+            .and_then(|refarg| super::as_strings(*refarg))
+            .unwrap_or_default();
+
+        let value = m
+            .get(InputFieldKind::Value.into())
+            .and_then(|refarg| refarg.as_str())
+            .map(String::from);
+
+        Ok(InputField {
+            type_,
+            requirement,
+            alternates,
+            value,
+        })
+    }
+}
+
+/// Input field names.
+pub enum InputKind {
+    Name,
+    Ssid,
+    Identity,
+    Passphrase,
+    PreviousPassphrase,
+    Wps,
+    Username,
+    Password,
+}
+
+impl From<InputKind> for &'static str {
+    fn from(kind: InputKind) -> Self {
+        match kind {
+            InputKind::Name => "Name",
+            InputKind::Ssid => "SSID",
+            InputKind::Identity => "Identity",
+            InputKind::Passphrase => "Passphrase",
+            InputKind::PreviousPassphrase => "PreviousPassphrase",
+            InputKind::Wps => "WPS",
+            InputKind::Username => "Username",
+            InputKind::Password => "Password",
+        }
+    }
+}
+
+/// Input field description keys.
+pub enum InputFieldKind {
+    Type,
+    Requirement,
+    Alternates,
+    Value,
+}
+
+impl From<InputFieldKind> for &'static str {
+    fn from(kind: InputFieldKind) -> Self {
+        match kind {
+            InputFieldKind::Type => "Type",
+            InputFieldKind::Requirement => "Requirement",
+            InputFieldKind::Alternates => "Alternates",
+            InputFieldKind::Value => "Value",
+        }
+    }
+}
+
+/// Kind of value expected for an input field.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum InputType {
+    String,
+    Ssid,
+    Psk,
+    Wep,
+    Passphrase,
+    Response,
+    WpsPin,
+    /// Password, as requested by the VPN agent
+    Password,
+    /// Checkbox, as requested by the VPN agent
+    Boolean,
+    Unknown(String),
+}
+
+impl FromStr for InputType {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "string" => Ok(InputType::String),
+            "ssid" => Ok(InputType::Ssid),
+            "psk" => Ok(InputType::Psk),
+            "wep" => Ok(InputType::Wep),
+            "passphrase" => Ok(InputType::Passphrase),
+            "response" => Ok(InputType::Response),
+            "wpspin" => Ok(InputType::WpsPin),
+            "password" => Ok(InputType::Password),
+            "boolean" => Ok(InputType::Boolean),
+            _ => Ok(InputType::Unknown(s.to_string())),
+        }
+    }
+}
+
+/// Whether an input field has to be given.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Requirement {
+    Mandatory,
+    Optional,
+    /// Only needed if none of the alternates pointing here are given
+    Alternate,
+    /// Not an input; the value is given for information only
+    Informational,
+    Unknown(String),
+}
+
+impl FromStr for Requirement {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mandatory" => Ok(Requirement::Mandatory),
+            "optional" => Ok(Requirement::Optional),
+            "alternate" => Ok(Requirement::Alternate),
+            "informational" => Ok(Requirement::Informational),
+            _ => Ok(Requirement::Unknown(s.to_string())),
+        }
+    }
+}
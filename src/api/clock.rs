@@ -0,0 +1,275 @@
+use dbus::message::{MatchRule, MessageType, SignalArgs};
+use dbus::nonblock::{NonblockReply, Proxy, SyncConnection};
+
+use super::gen::manager::{Clock as IClock, ClockPropertyChanged};
+use super::retry::RetryPolicy;
+use super::signal::SignalStream;
+use super::{Error as ApiError, FromProperties, PropertyError, RefArgMap};
+use std::borrow::Cow;
+use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Futures-aware wrapper struct for connman Clock object.
+#[derive(Clone)]
+pub struct Clock<C> {
+    proxy: Proxy<'static, C>,
+}
+
+impl<C> Clock<C> {
+    pub fn new(connection: C, timeout: Duration) -> Self {
+        Clock {
+            proxy: Self::proxy(timeout, connection),
+        }
+    }
+
+    pub fn proxy(timeout: Duration, conn: C) -> Proxy<'static, C> {
+        let proxy = Proxy::new("net.connman", "/", timeout, conn);
+        proxy
+    }
+}
+
+impl<T: NonblockReply, C: Deref<Target = T>> Clock<C> {
+    pub async fn get_properties(&self) -> Result<Properties, ApiError> {
+        let a = IClock::get_properties(&self.proxy).await?;
+        Ok(Properties::try_from(a)?)
+    }
+
+    pub async fn get_time(&self) -> Result<SystemTime, ApiError> {
+        let a = IClock::get_properties(&self.proxy).await?;
+        let secs = super::get_property::<u64>(&a, PropertyKind::Time.into())?;
+        Ok(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Set the system time; only allowed while `TimeUpdates` is manual.
+    pub async fn set_time(&self, time: SystemTime) -> Result<(), ApiError> {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| PropertyError::Cast(Cow::Borrowed(PropertyKind::Time.into())))?
+            .as_secs();
+        Ok(IClock::set_property(&self.proxy, PropertyKind::Time.into(), secs).await?)
+    }
+
+    pub async fn get_time_updates(&self) -> Result<Updates, ApiError> {
+        let a = IClock::get_properties(&self.proxy).await?;
+        Ok(super::get_property_fromstr::<Updates>(
+            &a,
+            PropertyKind::TimeUpdates.into(),
+        )?)
+    }
+
+    pub async fn set_time_updates(&self, updates: Updates) -> Result<(), ApiError> {
+        let updates: &'static str = updates.into();
+        Ok(IClock::set_property(&self.proxy, PropertyKind::TimeUpdates.into(), updates).await?)
+    }
+
+    pub async fn get_timezone(&self) -> Result<String, ApiError> {
+        let a = IClock::get_properties(&self.proxy).await?;
+        Ok(super::get_property_fromstr::<String>(
+            &a,
+            PropertyKind::Timezone.into(),
+        )?)
+    }
+
+    /// Set the timezone, e.g. `"Europe/Berlin"`; only allowed while
+    /// `TimezoneUpdates` is manual.
+    pub async fn set_timezone(&self, timezone: &str) -> Result<(), ApiError> {
+        Ok(IClock::set_property(&self.proxy, PropertyKind::Timezone.into(), timezone).await?)
+    }
+
+    pub async fn get_timezone_updates(&self) -> Result<Updates, ApiError> {
+        let a = IClock::get_properties(&self.proxy).await?;
+        Ok(super::get_property_fromstr::<Updates>(
+            &a,
+            PropertyKind::TimezoneUpdates.into(),
+        )?)
+    }
+
+    pub async fn set_timezone_updates(&self, updates: Updates) -> Result<(), ApiError> {
+        let updates: &'static str = updates.into();
+        Ok(
+            IClock::set_property(&self.proxy, PropertyKind::TimezoneUpdates.into(), updates)
+                .await?,
+        )
+    }
+
+    pub async fn get_timeservers(&self) -> Result<Vec<String>, ApiError> {
+        let a = IClock::get_properties(&self.proxy).await?;
+        Ok(Vec::<String>::from_properties(
+            &a,
+            PropertyKind::Timeservers.into(),
+        )?)
+    }
+
+    pub async fn set_timeservers(&self, timeservers: Vec<String>) -> Result<(), ApiError> {
+        Ok(
+            IClock::set_property(&self.proxy, PropertyKind::Timeservers.into(), timeservers)
+                .await?,
+        )
+    }
+}
+
+impl Clock<Arc<SyncConnection>> {
+    /// Subscribe to changes of the clock properties.
+    ///
+    /// The underlying match rule is removed once the returned stream is dropped.
+    pub async fn events(&self) -> Result<SignalStream<ClockChange>, ApiError> {
+        let rule = MatchRule::new()
+            .with_type(MessageType::Signal)
+            .with_sender("net.connman")
+            .with_path("/")
+            .with_interface(ClockPropertyChanged::INTERFACE)
+            .with_member(ClockPropertyChanged::NAME);
+
+        SignalStream::new(
+            self.proxy.connection.clone(),
+            rule,
+            &RetryPolicy::default(),
+            |msg| ClockPropertyChanged::from_message(&msg).and_then(ClockChange::from_signal),
+        )
+        .await
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Properties {
+    /// Current system time; not set if unavailable
+    pub time: Option<SystemTime>,
+    pub time_updates: Updates,
+    /// Current timezone; not set if unavailable
+    pub timezone: Option<String>,
+    pub timezone_updates: Updates,
+    /// List of timeservers, in order of preference
+    pub timeservers: Vec<String>,
+}
+
+impl Properties {
+    pub fn try_from(props: RefArgMap) -> Result<Self, PropertyError> {
+        let time: Option<u64> = FromProperties::from_properties(&props, PropertyKind::Time.into())?;
+        let time_updates = Updates::from_properties(&props, PropertyKind::TimeUpdates.into())?;
+        let timezone: Option<String> =
+            FromProperties::from_properties(&props, PropertyKind::Timezone.into())?;
+        let timezone_updates =
+            Updates::from_properties(&props, PropertyKind::TimezoneUpdates.into())?;
+        let timeservers: Vec<String> =
+            FromProperties::from_properties(&props, PropertyKind::Timeservers.into())?;
+
+        Ok(Properties {
+            time: time.map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            time_updates,
+            timezone,
+            timezone_updates,
+            timeservers,
+        })
+    }
+}
+
+/// A single clock property change.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ClockChange {
+    Time(SystemTime),
+    TimeUpdates(Updates),
+    Timezone(String),
+    TimezoneUpdates(Updates),
+    Timeservers(Vec<String>),
+}
+
+impl ClockChange {
+    fn from_signal(signal: ClockPropertyChanged) -> Option<Self> {
+        let kind = PropertyKind::from_str(&signal.name).ok()?;
+        let mut props = RefArgMap::new();
+        props.insert(signal.name, signal.value);
+
+        let change = match kind {
+            PropertyKind::Time => u64::from_properties(&props, kind.into())
+                .map(|secs| ClockChange::Time(UNIX_EPOCH + Duration::from_secs(secs))),
+            PropertyKind::TimeUpdates => {
+                Updates::from_properties(&props, kind.into()).map(ClockChange::TimeUpdates)
+            }
+            PropertyKind::Timezone => {
+                String::from_properties(&props, kind.into()).map(ClockChange::Timezone)
+            }
+            PropertyKind::TimezoneUpdates => {
+                Updates::from_properties(&props, kind.into()).map(ClockChange::TimezoneUpdates)
+            }
+            PropertyKind::Timeservers => {
+                Vec::<String>::from_properties(&props, kind.into()).map(ClockChange::Timeservers)
+            }
+        };
+        change.ok()
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum PropertyKind {
+    Time,
+    TimeUpdates,
+    Timezone,
+    TimezoneUpdates,
+    Timeservers,
+}
+
+impl From<PropertyKind> for &'static str {
+    fn from(prop: PropertyKind) -> Self {
+        match prop {
+            PropertyKind::Time => "Time",
+            PropertyKind::TimeUpdates => "TimeUpdates",
+            PropertyKind::Timezone => "Timezone",
+            PropertyKind::TimezoneUpdates => "TimezoneUpdates",
+            PropertyKind::Timeservers => "Timeservers",
+        }
+    }
+}
+
+impl FromStr for PropertyKind {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Time" => Ok(PropertyKind::Time),
+            "TimeUpdates" => Ok(PropertyKind::TimeUpdates),
+            "Timezone" => Ok(PropertyKind::Timezone),
+            "TimezoneUpdates" => Ok(PropertyKind::TimezoneUpdates),
+            "Timeservers" => Ok(PropertyKind::Timeservers),
+            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+        }
+    }
+}
+
+/// Whether a clock setting is updated automatically or only by the user.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Updates {
+    Manual,
+    Auto,
+}
+
+impl FromStr for Updates {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "manual" => Ok(Updates::Manual),
+            "auto" => Ok(Updates::Auto),
+            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+        }
+    }
+}
+
+impl From<Updates> for &'static str {
+    fn from(updates: Updates) -> Self {
+        match updates {
+            Updates::Manual => "manual",
+            Updates::Auto => "auto",
+        }
+    }
+}
+
+impl FromProperties for Updates {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        super::get_property_fromstr::<Self>(properties, prop_name)
+    }
+}
@@ -0,0 +1,210 @@
+//! Data usage counters (`net.connman.Counter`)
+
+use dbus::nonblock::{Proxy, SyncConnection};
+use dbus::Message;
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::stream::{Stream, StreamExt};
+
+use super::export::{call_no_reply, error_reply, invalid_args, unique_path, Exported, Reply};
+use super::gen::manager::Manager as IManager;
+use super::{Error, FromProperties, PropertyError, RefArgMap};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+const INTERFACE: &str = "net.connman.Counter";
+
+/// Usage reported for a single service.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ServiceUsage {
+    pub service: dbus::Path<'static>,
+    /// Usage on the home network
+    pub home: Stats,
+    /// Usage while roaming
+    pub roaming: Stats,
+}
+
+/// Counter values, accumulated since the service counters were last reset.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    pub rx_bytes: u32,
+    pub rx_packets: u32,
+    pub rx_errors: u32,
+    pub rx_dropped: u32,
+    pub tx_bytes: u32,
+    pub tx_packets: u32,
+    pub tx_errors: u32,
+    pub tx_dropped: u32,
+    /// Time connected, in seconds
+    pub time: u32,
+}
+
+impl Stats {
+    /// Applies the values present in `props`; connman only sends the values
+    /// that changed after the first report.
+    fn update(&mut self, props: &RefArgMap) -> Result<(), PropertyError> {
+        let set = |kind: StatsKind, field: &mut u32| -> Result<(), PropertyError> {
+            if let Some(val) = Option::<u32>::from_properties(props, kind.into())? {
+                *field = val;
+            }
+            Ok(())
+        };
+
+        set(StatsKind::RxBytes, &mut self.rx_bytes)?;
+        set(StatsKind::RxPackets, &mut self.rx_packets)?;
+        set(StatsKind::RxErrors, &mut self.rx_errors)?;
+        set(StatsKind::RxDropped, &mut self.rx_dropped)?;
+        set(StatsKind::TxBytes, &mut self.tx_bytes)?;
+        set(StatsKind::TxPackets, &mut self.tx_packets)?;
+        set(StatsKind::TxErrors, &mut self.tx_errors)?;
+        set(StatsKind::TxDropped, &mut self.tx_dropped)?;
+        set(StatsKind::Time, &mut self.time)?;
+        Ok(())
+    }
+}
+
+/// Counter value fields.
+#[derive(Copy, Clone, Debug)]
+pub enum StatsKind {
+    RxBytes,
+    RxPackets,
+    RxErrors,
+    RxDropped,
+    TxBytes,
+    TxPackets,
+    TxErrors,
+    TxDropped,
+    Time,
+}
+
+impl From<StatsKind> for &'static str {
+    fn from(kind: StatsKind) -> Self {
+        match kind {
+            StatsKind::RxBytes => "RX.Bytes",
+            StatsKind::RxPackets => "RX.Packets",
+            StatsKind::RxErrors => "RX.Errors",
+            StatsKind::RxDropped => "RX.Dropped",
+            StatsKind::TxBytes => "TX.Bytes",
+            StatsKind::TxPackets => "TX.Packets",
+            StatsKind::TxErrors => "TX.Errors",
+            StatsKind::TxDropped => "TX.Dropped",
+            StatsKind::Time => "Time",
+        }
+    }
+}
+
+/// Stream of usage reports from a counter registered with the connman
+/// Manager.
+///
+/// The counter is unregistered when this is dropped. The stream ends if
+/// connman releases the counter.
+pub struct Counter {
+    path: dbus::Path<'static>,
+    manager: Option<Proxy<'static, Arc<SyncConnection>>>,
+    inner: UnboundedReceiver<ServiceUsage>,
+    _exported: Exported,
+}
+
+impl Counter {
+    pub(crate) async fn new(
+        manager: Proxy<'static, Arc<SyncConnection>>,
+        accuracy: u32,
+        period: u32,
+    ) -> Result<Self, Error> {
+        let path = unique_path("counter");
+        let (sender, inner) = mpsc::unbounded();
+
+        // Last known values per service, since updates only carry changes.
+        let totals = Arc::new(Mutex::new(HashMap::new()));
+        let exported = Exported::new(&manager.connection, path.clone(), INTERFACE, move |msg| {
+            let reply = dispatch(&sender, &totals, &msg);
+            Box::pin(async move { reply }) as Reply
+        });
+
+        IManager::register_counter(&manager, path.clone(), accuracy, period).await?;
+
+        Ok(Counter {
+            path,
+            manager: Some(manager),
+            inner,
+            _exported: exported,
+        })
+    }
+
+    pub fn path(&self) -> &dbus::Path<'static> {
+        &self.path
+    }
+
+    pub async fn unregister(mut self) -> Result<(), Error> {
+        match self.manager.take() {
+            Some(manager) => Ok(IManager::unregister_counter(&manager, self.path.clone()).await?),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Stream for Counter {
+    type Item = ServiceUsage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        if let Some(manager) = self.manager.take() {
+            call_no_reply(
+                &manager.connection,
+                "net.connman",
+                "/".into(),
+                "net.connman.Manager",
+                "UnregisterCounter",
+                (self.path.clone(),),
+            );
+        }
+    }
+}
+
+fn dispatch(
+    sender: &UnboundedSender<ServiceUsage>,
+    totals: &Mutex<HashMap<dbus::Path<'static>, (Stats, Stats)>>,
+    msg: &Message,
+) -> Message {
+    match msg.member().as_deref() {
+        Some("Usage") => {
+            let (service, home, roaming) = match msg.read3::<dbus::Path, RefArgMap, RefArgMap>() {
+                Ok(args) => args,
+                Err(_) => return invalid_args(msg),
+            };
+            let service = service.into_static();
+
+            let mut totals = totals.lock().unwrap();
+            let entry = totals.entry(service.clone()).or_default();
+            if let Err(e) = entry.0.update(&home).and_then(|_| entry.1.update(&roaming)) {
+                return error_reply(
+                    msg,
+                    "org.freedesktop.DBus.Error.InvalidArgs",
+                    &e.to_string(),
+                );
+            }
+
+            let _ = sender.unbounded_send(ServiceUsage {
+                service,
+                home: entry.0.clone(),
+                roaming: entry.1.clone(),
+            });
+            msg.method_return()
+        }
+        Some("Release") => {
+            sender.close_channel();
+            msg.method_return()
+        }
+        _ => error_reply(
+            msg,
+            "org.freedesktop.DBus.Error.UnknownMethod",
+            "Unknown method",
+        ),
+    }
+}
@@ -0,0 +1,115 @@
+//! Helpers for serving D-Bus objects that connman calls back into
+
+use dbus::arg::AppendAll;
+use dbus::channel::{MatchingReceiver, Sender, Token};
+use dbus::message::MatchRule;
+use dbus::nonblock::SyncConnection;
+use dbus::strings::ErrorName;
+use dbus::Message;
+
+use std::ffi::CString;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub(crate) type Reply = Pin<Box<dyn Future<Output = Message> + Send>>;
+
+/// Method-call handler for an object exported on the connection.
+///
+/// Calls are dispatched while the value is alive, and dropping it stops the
+/// dispatch.
+pub(crate) struct Exported {
+    connection: Arc<SyncConnection>,
+    token: Token,
+}
+
+impl Exported {
+    /// Dispatches every method call to `interface` on `path` to `handler`,
+    /// spawning the returned future and sending back the reply it resolves to.
+    pub(crate) fn new<F>(
+        connection: &Arc<SyncConnection>,
+        path: dbus::Path<'static>,
+        interface: &'static str,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Message) -> Reply + Send + 'static,
+    {
+        let rule = MatchRule::new_method_call()
+            .with_path(path)
+            .with_interface(interface);
+
+        // Only keep a weak reference, since the connection owns the callback.
+        let weak = Arc::downgrade(connection);
+        let token = connection.start_receive(
+            rule,
+            Box::new(move |msg, _| {
+                if let Some(connection) = weak.upgrade() {
+                    let no_reply = msg.get_no_reply();
+                    let reply = handler(msg);
+                    tokio::spawn(async move {
+                        let reply = reply.await;
+                        if !no_reply {
+                            let _ = connection.send(reply);
+                        }
+                    });
+                }
+                true
+            }),
+        );
+
+        Exported {
+            connection: connection.clone(),
+            token,
+        }
+    }
+}
+
+impl Drop for Exported {
+    fn drop(&mut self) {
+        let _ = self.connection.stop_receive(self.token);
+    }
+}
+
+/// Returns an object path not yet handed out by this process, for objects
+/// whose path the caller does not care about.
+pub(crate) fn unique_path(kind: &str) -> dbus::Path<'static> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    dbus::Path::from(format!("/connman_rs/{}{}", kind, n))
+}
+
+/// Builds a D-Bus error reply to `msg`.
+pub(crate) fn error_reply(msg: &Message, name: &'static str, description: &str) -> Message {
+    let description = CString::new(description).unwrap_or_default();
+    msg.error(&ErrorName::from(name), &description)
+}
+
+/// Builds the reply for a call whose arguments could not be read.
+pub(crate) fn invalid_args(msg: &Message) -> Message {
+    error_reply(
+        msg,
+        "org.freedesktop.DBus.Error.InvalidArgs",
+        "Invalid arguments",
+    )
+}
+
+/// Sends a method call without waiting for, or asking for, a reply.
+///
+/// Used from `Drop` implementations, which cannot await.
+pub(crate) fn call_no_reply<A: AppendAll>(
+    connection: &SyncConnection,
+    destination: &'static str,
+    path: dbus::Path<'static>,
+    interface: &'static str,
+    member: &'static str,
+    args: A,
+) {
+    if let Ok(mut msg) = Message::new_method_call(destination, path, interface, member) {
+        msg.append_all(args);
+        msg.set_no_reply(true);
+        let _ = connection.send(msg);
+    }
+}
@@ -4,5 +4,9 @@
 #![allow(clippy::type_complexity)]
 
 pub mod manager;
+pub mod peer;
 pub mod service;
+pub mod session;
 pub mod technology;
+pub mod vpn_connection;
+pub mod vpn_manager;
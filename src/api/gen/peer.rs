@@ -0,0 +1,66 @@
+// This code was autogenerated with `dbus-codegen-rust -g -i net.connman -m None -c nonblock`, see https://github.com/diwic/dbus-rs
+use dbus as dbus;
+#[allow(unused_imports)]
+use dbus::arg;
+use dbus::nonblock;
+
+pub trait OrgFreedesktopDBusIntrospectable {
+    fn introspect(&self) -> nonblock::MethodReply<String>;
+}
+
+impl<'a, T: nonblock::NonblockReply, C: ::std::ops::Deref<Target=T>> OrgFreedesktopDBusIntrospectable for nonblock::Proxy<'a, C> {
+
+    fn introspect(&self) -> nonblock::MethodReply<String> {
+        self.method_call("org.freedesktop.DBus.Introspectable", "Introspect", ())
+            .and_then(|r: (String, )| Ok(r.0, ))
+    }
+}
+
+pub trait Peer {
+    fn get_properties(&self) -> nonblock::MethodReply<::std::collections::HashMap<String, arg::Variant<Box<dyn arg::RefArg + 'static>>>>;
+    fn connect(&self) -> nonblock::MethodReply<()>;
+    fn disconnect(&self) -> nonblock::MethodReply<()>;
+}
+
+impl<'a, T: nonblock::NonblockReply, C: ::std::ops::Deref<Target=T>> Peer for nonblock::Proxy<'a, C> {
+
+    fn get_properties(&self) -> nonblock::MethodReply<::std::collections::HashMap<String, arg::Variant<Box<dyn arg::RefArg + 'static>>>> {
+        self.method_call("net.connman.Peer", "GetProperties", ())
+            .and_then(|r: (::std::collections::HashMap<String, arg::Variant<Box<dyn arg::RefArg + 'static>>>, )| Ok(r.0, ))
+    }
+
+    fn connect(&self) -> nonblock::MethodReply<()> {
+        self.method_call("net.connman.Peer", "Connect", ())
+    }
+
+    fn disconnect(&self) -> nonblock::MethodReply<()> {
+        self.method_call("net.connman.Peer", "Disconnect", ())
+    }
+}
+
+#[derive(Debug)]
+pub struct PeerPropertyChanged {
+    pub name: String,
+    pub value: arg::Variant<Box<dyn arg::RefArg + 'static>>,
+}
+
+impl arg::AppendAll for PeerPropertyChanged {
+    fn append(&self, i: &mut arg::IterAppend) {
+        arg::RefArg::append(&self.name, i);
+        arg::RefArg::append(&self.value, i);
+    }
+}
+
+impl arg::ReadAll for PeerPropertyChanged {
+    fn read(i: &mut arg::Iter) -> Result<Self, arg::TypeMismatchError> {
+        Ok(PeerPropertyChanged {
+            name: i.read()?,
+            value: i.read()?,
+        })
+    }
+}
+
+impl dbus::message::SignalArgs for PeerPropertyChanged {
+    const NAME: &'static str = "PropertyChanged";
+    const INTERFACE: &'static str = "net.connman.Peer";
+}
@@ -0,0 +1,31 @@
+// This code was autogenerated with `dbus-codegen-rust -g -i net.connman -m None -c nonblock`, see https://github.com/diwic/dbus-rs
+use dbus as dbus;
+#[allow(unused_imports)]
+use dbus::arg;
+use dbus::nonblock;
+
+pub trait Session {
+    fn destroy(&self) -> nonblock::MethodReply<()>;
+    fn connect(&self) -> nonblock::MethodReply<()>;
+    fn disconnect(&self) -> nonblock::MethodReply<()>;
+    fn change<I1: arg::Arg + arg::Append>(&self, name: &str, value: I1) -> nonblock::MethodReply<()>;
+}
+
+impl<'a, T: nonblock::NonblockReply, C: ::std::ops::Deref<Target=T>> Session for nonblock::Proxy<'a, C> {
+
+    fn destroy(&self) -> nonblock::MethodReply<()> {
+        self.method_call("net.connman.Session", "Destroy", ())
+    }
+
+    fn connect(&self) -> nonblock::MethodReply<()> {
+        self.method_call("net.connman.Session", "Connect", ())
+    }
+
+    fn disconnect(&self) -> nonblock::MethodReply<()> {
+        self.method_call("net.connman.Session", "Disconnect", ())
+    }
+
+    fn change<I1: arg::Arg + arg::Append>(&self, name: &str, value: I1) -> nonblock::MethodReply<()> {
+        self.method_call("net.connman.Session", "Change", (name, arg::Variant(value), ))
+    }
+}
@@ -0,0 +1,101 @@
+// This code was autogenerated with `dbus-codegen-rust -g -i net.connman.vpn -m None -c nonblock`, see https://github.com/diwic/dbus-rs
+use dbus as dbus;
+#[allow(unused_imports)]
+use dbus::arg;
+use dbus::nonblock;
+
+pub trait OrgFreedesktopDBusIntrospectable {
+    fn introspect(&self) -> nonblock::MethodReply<String>;
+}
+
+impl<'a, T: nonblock::NonblockReply, C: ::std::ops::Deref<Target=T>> OrgFreedesktopDBusIntrospectable for nonblock::Proxy<'a, C> {
+
+    fn introspect(&self) -> nonblock::MethodReply<String> {
+        self.method_call("org.freedesktop.DBus.Introspectable", "Introspect", ())
+            .and_then(|r: (String, )| Ok(r.0, ))
+    }
+}
+
+pub trait Manager {
+    fn create(&self, properties: ::std::collections::HashMap<&str, arg::Variant<Box<dyn arg::RefArg>>>) -> nonblock::MethodReply<dbus::Path<'static>>;
+    fn remove(&self, identifier: dbus::Path) -> nonblock::MethodReply<()>;
+    fn get_connections(&self) -> nonblock::MethodReply<Vec<(dbus::Path<'static>, ::std::collections::HashMap<String, arg::Variant<Box<dyn arg::RefArg + 'static>>>)>>;
+    fn register_agent(&self, path: dbus::Path) -> nonblock::MethodReply<()>;
+    fn unregister_agent(&self, path: dbus::Path) -> nonblock::MethodReply<()>;
+}
+
+impl<'a, T: nonblock::NonblockReply, C: ::std::ops::Deref<Target=T>> Manager for nonblock::Proxy<'a, C> {
+
+    fn create(&self, properties: ::std::collections::HashMap<&str, arg::Variant<Box<dyn arg::RefArg>>>) -> nonblock::MethodReply<dbus::Path<'static>> {
+        self.method_call("net.connman.vpn.Manager", "Create", (properties, ))
+            .and_then(|r: (dbus::Path<'static>, )| Ok(r.0, ))
+    }
+
+    fn remove(&self, identifier: dbus::Path) -> nonblock::MethodReply<()> {
+        self.method_call("net.connman.vpn.Manager", "Remove", (identifier, ))
+    }
+
+    fn get_connections(&self) -> nonblock::MethodReply<Vec<(dbus::Path<'static>, ::std::collections::HashMap<String, arg::Variant<Box<dyn arg::RefArg + 'static>>>)>> {
+        self.method_call("net.connman.vpn.Manager", "GetConnections", ())
+            .and_then(|r: (Vec<(dbus::Path<'static>, ::std::collections::HashMap<String, arg::Variant<Box<dyn arg::RefArg + 'static>>>)>, )| Ok(r.0, ))
+    }
+
+    fn register_agent(&self, path: dbus::Path) -> nonblock::MethodReply<()> {
+        self.method_call("net.connman.vpn.Manager", "RegisterAgent", (path, ))
+    }
+
+    fn unregister_agent(&self, path: dbus::Path) -> nonblock::MethodReply<()> {
+        self.method_call("net.connman.vpn.Manager", "UnregisterAgent", (path, ))
+    }
+}
+
+#[derive(Debug)]
+pub struct ManagerConnectionAdded {
+    pub identifier: dbus::Path<'static>,
+    pub properties: ::std::collections::HashMap<String, arg::Variant<Box<dyn arg::RefArg + 'static>>>,
+}
+
+impl arg::AppendAll for ManagerConnectionAdded {
+    fn append(&self, i: &mut arg::IterAppend) {
+        arg::RefArg::append(&self.identifier, i);
+        arg::RefArg::append(&self.properties, i);
+    }
+}
+
+impl arg::ReadAll for ManagerConnectionAdded {
+    fn read(i: &mut arg::Iter) -> Result<Self, arg::TypeMismatchError> {
+        Ok(ManagerConnectionAdded {
+            identifier: i.read()?,
+            properties: i.read()?,
+        })
+    }
+}
+
+impl dbus::message::SignalArgs for ManagerConnectionAdded {
+    const NAME: &'static str = "ConnectionAdded";
+    const INTERFACE: &'static str = "net.connman.vpn.Manager";
+}
+
+#[derive(Debug)]
+pub struct ManagerConnectionRemoved {
+    pub identifier: dbus::Path<'static>,
+}
+
+impl arg::AppendAll for ManagerConnectionRemoved {
+    fn append(&self, i: &mut arg::IterAppend) {
+        arg::RefArg::append(&self.identifier, i);
+    }
+}
+
+impl arg::ReadAll for ManagerConnectionRemoved {
+    fn read(i: &mut arg::Iter) -> Result<Self, arg::TypeMismatchError> {
+        Ok(ManagerConnectionRemoved {
+            identifier: i.read()?,
+        })
+    }
+}
+
+impl dbus::message::SignalArgs for ManagerConnectionRemoved {
+    const NAME: &'static str = "ConnectionRemoved";
+    const INTERFACE: &'static str = "net.connman.vpn.Manager";
+}
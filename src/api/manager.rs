@@ -1,24 +1,41 @@
 use dbus::arg::{RefArg, Variant};
+use dbus::message::{MatchRule, MessageType, SignalArgs};
 use dbus::nonblock::{NonblockReply, Proxy, SyncConnection};
+use dbus::Message;
 
 #[cfg(feature = "introspection")]
 use xml::reader::EventReader;
 
-use super::gen::manager::Manager as IManager;
+use super::agent::{Agent, AgentRegistration};
+use super::counter::Counter;
+use super::gen::manager::{
+    Manager as IManager, ManagerPeersChanged, ManagerPropertyChanged, ManagerServicesChanged,
+    ManagerTechnologyAdded, ManagerTechnologyRemoved,
+};
+use super::gen::service::Service as IService;
+use super::peer::{Peer, PeerServiceRegistration, PeerServiceSpec};
+use super::private_network::PrivateNetwork;
+use super::provider::ProviderSettings;
+use super::retry::RetryPolicy;
 use super::service::{Properties as ServiceProperties, Service};
+use super::session::{Session, SessionSettings};
+use super::signal::SignalStream;
 use super::technology::Technology;
-use super::Error;
+use super::{Error, FromProperties, ParseMode, PropertyError, RefArgMap};
+use std::borrow::Cow;
 use std::future::Future;
 use std::ops::Deref;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Futures-aware wrapper struct for connman Manager object.
 #[derive(Clone)]
 pub struct Manager<C> {
-    proxy: Proxy<'static, C>,
-    timeout: Duration,
-    // TODO: Signal subscription/dispatcher
+    pub(crate) proxy: Proxy<'static, C>,
+    pub(crate) timeout: Duration,
+    pub(crate) parse_mode: ParseMode,
+    pub(crate) retry: RetryPolicy,
 }
 
 impl<C> Manager<C> {
@@ -26,9 +43,33 @@ impl<C> Manager<C> {
         Manager {
             proxy: Self::proxy(timeout, connection),
             timeout,
+            parse_mode: ParseMode::Strict,
+            retry: RetryPolicy::default(),
         }
     }
 
+    /// Set how the properties of the services returned by this manager are
+    /// parsed; defaults to `ParseMode::Strict`.
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
+    pub fn parse_mode(&self) -> ParseMode {
+        self.parse_mode
+    }
+
+    /// Set the policy for retrying failed calls, used by this manager and the
+    /// services and technologies it returns.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
     pub fn proxy(timeout: Duration, conn: C) -> Proxy<'static, C> {
         let proxy = Proxy::new("net.connman", "/", timeout, conn);
         proxy
@@ -37,23 +78,138 @@ impl<C> Manager<C> {
 
 impl<T: NonblockReply, C: Deref<Target = T> + Clone> Manager<C> {
     pub async fn get_technologies(&self) -> Result<Vec<Technology<C>>, Error> {
+        Ok(self
+            .get_technologies_partial()
+            .await?
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect())
+    }
+
+    /// Like `get_technologies`, but also returns the path and parse error of
+    /// each technology that couldn't be parsed, in connman's order.
+    pub async fn get_technologies_partial(
+        &self,
+    ) -> Result<Vec<Result<Technology<C>, (dbus::Path<'static>, PropertyError)>>, Error> {
         let connclone = self.proxy.connection.clone();
 
-        let v = IManager::get_technologies(&self.proxy).await?;
+        let v = self
+            .retry
+            .run(|| IManager::get_technologies(&self.proxy))
+            .await?;
         Ok(v.into_iter()
-            .filter_map(|(path, args)| {
-                Technology::new(connclone.clone(), path, args, self.timeout).ok()
+            .map(|(path, args)| {
+                Technology::try_new(
+                    connclone.clone(),
+                    path.clone(),
+                    args,
+                    self.timeout,
+                    self.parse_mode,
+                    self.retry.clone(),
+                )
+                .map_err(|e| (path, e))
             })
             .collect())
     }
 
     pub async fn get_services(&self) -> Result<Vec<Service<C>>, Error> {
+        Ok(self
+            .get_services_partial()
+            .await?
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect())
+    }
+
+    /// Like `get_services`, but also returns the path and parse error of each
+    /// service that couldn't be parsed, in connman's order.
+    pub async fn get_services_partial(
+        &self,
+    ) -> Result<Vec<Result<Service<C>, (dbus::Path<'static>, PropertyError)>>, Error> {
+        let connclone = self.proxy.connection.clone();
+
+        let v = self
+            .retry
+            .run(|| IManager::get_services(&self.proxy))
+            .await?;
+        Ok(v.into_iter()
+            .map(|(path, args)| {
+                Service::try_new(
+                    connclone.clone(),
+                    path.clone(),
+                    args,
+                    self.timeout,
+                    self.parse_mode,
+                    self.retry.clone(),
+                )
+                .map_err(|e| (path, e))
+            })
+            .collect())
+    }
+
+    /// Create a VPN provider and connect to it, returning the new VPN service.
+    ///
+    /// If the new service can't be read back, the provider is removed again
+    /// before the error is returned.
+    pub async fn connect_provider(&self, settings: ProviderSettings) -> Result<Service<C>, Error> {
+        settings.validate()?;
+
+        let path = self
+            .retry
+            .run_non_idempotent(|| IManager::connect_provider(&self.proxy, settings.to_dict()))
+            .await?;
+        let proxy = Service::proxy(path.clone(), self.timeout, self.proxy.connection.clone());
+        let service = self
+            .retry
+            .run(|| IService::get_properties(&proxy))
+            .await
+            .and_then(|args| {
+                Ok(Service::try_new(
+                    self.proxy.connection.clone(),
+                    path.clone(),
+                    args,
+                    self.timeout,
+                    self.parse_mode,
+                    self.retry.clone(),
+                )?)
+            });
+        if service.is_err() {
+            // Best effort; the original error is the one worth reporting.
+            let _ = self
+                .retry
+                .run(|| IManager::remove_provider(&self.proxy, path.clone()))
+                .await;
+        }
+        service
+    }
+
+    pub async fn remove_provider(&self, service: &Service<C>) -> Result<(), Error> {
+        self.retry
+            .run(|| IManager::remove_provider(&self.proxy, service.path().clone()))
+            .await
+    }
+
+    pub async fn get_peers(&self) -> Result<Vec<Peer<C>>, Error> {
+        Ok(self
+            .get_peers_partial()
+            .await?
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect())
+    }
+
+    /// Like `get_peers`, but also returns the path and parse error of each
+    /// peer that couldn't be parsed, in connman's order.
+    pub async fn get_peers_partial(
+        &self,
+    ) -> Result<Vec<Result<Peer<C>, (dbus::Path<'static>, PropertyError)>>, Error> {
         let connclone = self.proxy.connection.clone();
 
-        let v = IManager::get_services(&self.proxy).await?;
+        let v = self.retry.run(|| IManager::get_peers(&self.proxy)).await?;
         Ok(v.into_iter()
-            .filter_map(|(path, args)| {
-                Service::new(connclone.clone(), path, args, self.timeout).ok()
+            .map(|(path, args)| {
+                Peer::try_new(connclone.clone(), path.clone(), args, self.timeout)
+                    .map_err(|e| (path, e))
             })
             .collect())
     }
@@ -64,23 +220,311 @@ impl<T: NonblockReply, C: Deref<Target = T>> Manager<C> {
     pub async fn introspect(&self) -> Result<EventReader<std::io::Cursor<Vec<u8>>>, Error> {
         use crate::api::gen::manager::OrgFreedesktopDBusIntrospectable as Introspectable;
 
-        let s = Introspectable::introspect(&self.proxy).await?;
+        let s = self
+            .retry
+            .run(|| Introspectable::introspect(&self.proxy))
+            .await?;
         let rdr = std::io::Cursor::new(s.into_bytes());
         Ok(EventReader::new(rdr))
     }
 
+    pub async fn get_properties(&self) -> Result<ManagerProperties, Error> {
+        let a = self
+            .retry
+            .run(|| IManager::get_properties(&self.proxy))
+            .await?;
+        Ok(ManagerProperties::try_from(a)?)
+    }
+
     pub async fn get_state(&self) -> Result<State, Error> {
-        let a = IManager::get_properties(&self.proxy).await?;
-        Ok(super::get_property_fromstr::<State>(&a, "State")?)
+        let a = self
+            .retry
+            .run(|| IManager::get_properties(&self.proxy))
+            .await?;
+        Ok(super::get_property_fromstr::<State>(
+            &a,
+            PropertyKind::State.into(),
+        )?)
     }
 
     pub async fn get_offline_mode(&self) -> Result<bool, Error> {
-        let a = IManager::get_properties(&self.proxy).await?;
-        Ok(super::get_property::<bool>(&a, "OfflineMode")?)
+        let a = self
+            .retry
+            .run(|| IManager::get_properties(&self.proxy))
+            .await?;
+        Ok(super::get_property::<bool>(
+            &a,
+            PropertyKind::OfflineMode.into(),
+        )?)
     }
 
     pub async fn set_offline_mode(&self, offline_mode: bool) -> Result<(), Error> {
-        Ok(IManager::set_property(&self.proxy, "OfflineMode", offline_mode).await?)
+        self.retry
+            .run(|| {
+                IManager::set_property(&self.proxy, PropertyKind::OfflineMode.into(), offline_mode)
+            })
+            .await
+    }
+
+    pub async fn get_session_mode(&self) -> Result<bool, Error> {
+        let a = self
+            .retry
+            .run(|| IManager::get_properties(&self.proxy))
+            .await?;
+        Ok(super::get_property::<bool>(
+            &a,
+            PropertyKind::SessionMode.into(),
+        )?)
+    }
+
+    pub async fn set_session_mode(&self, session_mode: bool) -> Result<(), Error> {
+        self.retry
+            .run(|| {
+                IManager::set_property(&self.proxy, PropertyKind::SessionMode.into(), session_mode)
+            })
+            .await
+    }
+}
+
+impl Manager<Arc<SyncConnection>> {
+    /// Subscribe to the signals emitted by the connman Manager object.
+    ///
+    /// The underlying match rule is removed once the returned stream is dropped.
+    /// See `SignalStream` on holding several overlapping streams at once.
+    pub async fn events(&self) -> Result<SignalStream<ManagerEvent>, Error> {
+        let rule = MatchRule::new()
+            .with_type(MessageType::Signal)
+            .with_sender("net.connman")
+            .with_path("/")
+            .with_interface("net.connman.Manager");
+
+        SignalStream::new(self.proxy.connection.clone(), rule, &self.retry, |msg| {
+            ManagerEvent::from_message(&msg)
+        })
+        .await
+    }
+
+    /// Subscribe to changes of the peer list.
+    ///
+    /// The underlying match rule is removed once the returned stream is dropped.
+    pub async fn peers_changed(&self) -> Result<SignalStream<PeersChanged>, Error> {
+        let rule = MatchRule::new()
+            .with_type(MessageType::Signal)
+            .with_sender("net.connman")
+            .with_path("/")
+            .with_interface(ManagerPeersChanged::INTERFACE)
+            .with_member(ManagerPeersChanged::NAME);
+
+        SignalStream::new(self.proxy.connection.clone(), rule, &self.retry, |msg| {
+            ManagerPeersChanged::from_message(&msg).map(|s| PeersChanged {
+                changed: s.changed,
+                removed: s.removed,
+            })
+        })
+        .await
+    }
+
+    /// Request a private network for a sandboxed workload.
+    pub async fn request_private_network(&self) -> Result<PrivateNetwork, Error> {
+        self.retry
+            .run_non_idempotent(|| PrivateNetwork::new(self.proxy.clone()))
+            .await
+    }
+
+    /// Advertise a P2P service; `master` marks this device as the one
+    /// providing the service.
+    pub async fn register_peer_service(
+        &self,
+        spec: PeerServiceSpec,
+        master: bool,
+    ) -> Result<PeerServiceRegistration, Error> {
+        self.retry
+            .run_non_idempotent(|| {
+                PeerServiceRegistration::new(self.proxy.clone(), spec.clone(), master)
+            })
+            .await
+    }
+
+    /// Export `agent` at `path` and register it as the connman Agent.
+    ///
+    /// The agent is exported once; only the `RegisterAgent` call is retried.
+    pub async fn register_agent<A: Agent>(
+        &self,
+        path: dbus::Path<'static>,
+        agent: A,
+    ) -> Result<AgentRegistration, Error> {
+        AgentRegistration::new(self.proxy.clone(), path, agent, &self.retry).await
+    }
+
+    /// Register a counter reporting per-service data usage every `period`
+    /// seconds, or whenever `accuracy` kilobytes have been transferred.
+    pub async fn register_counter(&self, accuracy: u32, period: u32) -> Result<Counter, Error> {
+        self.retry
+            .run_non_idempotent(|| Counter::new(self.proxy.clone(), accuracy, period))
+            .await
+    }
+
+    /// Create a session with the given settings, serving its notifier on the
+    /// connection.
+    pub async fn create_session(&self, settings: SessionSettings) -> Result<Session, Error> {
+        self.retry
+            .run_non_idempotent(|| Session::new(self.proxy.clone(), settings.clone()))
+            .await
+    }
+}
+
+/// Change of the peer list; `changed` holds every peer in order, but only with
+/// the properties that changed since the last signal.
+#[derive(Debug)]
+pub struct PeersChanged {
+    pub changed: Vec<(dbus::Path<'static>, RefArgMap)>,
+    pub removed: Vec<dbus::Path<'static>>,
+}
+
+/// Signals emitted by the connman Manager object.
+#[derive(Debug)]
+pub enum ManagerEvent {
+    /// A global property changed value
+    PropertyChanged {
+        name: String,
+        value: Variant<Box<dyn RefArg + 'static>>,
+    },
+    /// A technology was added, along with its properties
+    TechnologyAdded {
+        path: dbus::Path<'static>,
+        properties: RefArgMap,
+    },
+    /// A technology was removed
+    TechnologyRemoved { path: dbus::Path<'static> },
+    /// The service list changed; `changed` holds every service in order, but
+    /// only with the properties that changed since the last signal
+    ServicesChanged {
+        changed: Vec<(dbus::Path<'static>, RefArgMap)>,
+        removed: Vec<dbus::Path<'static>>,
+    },
+    /// The peer list changed, with the same semantics as `ServicesChanged`
+    PeersChanged {
+        changed: Vec<(dbus::Path<'static>, RefArgMap)>,
+        removed: Vec<dbus::Path<'static>>,
+    },
+}
+
+impl ManagerEvent {
+    fn from_message(msg: &Message) -> Option<Self> {
+        match &*msg.member()? {
+            ManagerPropertyChanged::NAME => {
+                ManagerPropertyChanged::from_message(msg).map(|s| ManagerEvent::PropertyChanged {
+                    name: s.name,
+                    value: s.value,
+                })
+            }
+            ManagerTechnologyAdded::NAME => {
+                ManagerTechnologyAdded::from_message(msg).map(|s| ManagerEvent::TechnologyAdded {
+                    path: s.path,
+                    properties: s.properties,
+                })
+            }
+            ManagerTechnologyRemoved::NAME => ManagerTechnologyRemoved::from_message(msg)
+                .map(|s| ManagerEvent::TechnologyRemoved { path: s.path }),
+            ManagerServicesChanged::NAME => {
+                ManagerServicesChanged::from_message(msg).map(|s| ManagerEvent::ServicesChanged {
+                    changed: s.changed,
+                    removed: s.removed,
+                })
+            }
+            ManagerPeersChanged::NAME => {
+                ManagerPeersChanged::from_message(msg).map(|s| ManagerEvent::PeersChanged {
+                    changed: s.changed,
+                    removed: s.removed,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Global connman properties, as held by the Manager object.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ManagerProperties {
+    pub state: State,
+    /// Set if all radios are disabled
+    pub offline_mode: bool,
+    /// Deprecated by connman; always false on current versions
+    pub session_mode: bool,
+}
+
+impl ManagerProperties {
+    pub fn try_from(props: RefArgMap) -> Result<Self, PropertyError> {
+        let state = State::from_properties(&props, PropertyKind::State.into())?;
+        let offline_mode = bool::from_properties(&props, PropertyKind::OfflineMode.into())?;
+        let session_mode: Option<bool> =
+            FromProperties::from_properties(&props, PropertyKind::SessionMode.into())?;
+
+        Ok(ManagerProperties {
+            state,
+            offline_mode,
+            session_mode: session_mode.unwrap_or(false),
+        })
+    }
+
+    /// Applies a `PropertyChanged` signal, returning whether it changed any
+    /// of the properties.
+    ///
+    /// Other events and unknown properties are ignored.
+    pub fn update(&mut self, event: &ManagerEvent) -> Result<bool, PropertyError> {
+        let (name, value) = match event {
+            ManagerEvent::PropertyChanged { name, value } => (name, value),
+            _ => return Ok(false),
+        };
+        let kind = match PropertyKind::from_str(name) {
+            Ok(kind) => kind,
+            Err(_) => return Ok(false),
+        };
+        let mut props = RefArgMap::new();
+        props.insert(name.clone(), Variant(value.0.box_clone()));
+
+        let old = self.clone();
+        match kind {
+            PropertyKind::State => self.state = State::from_properties(&props, kind.into())?,
+            PropertyKind::OfflineMode => {
+                self.offline_mode = bool::from_properties(&props, kind.into())?
+            }
+            PropertyKind::SessionMode => {
+                self.session_mode = bool::from_properties(&props, kind.into())?
+            }
+        }
+        Ok(*self != old)
+    }
+}
+
+/// Manager property fields.
+#[derive(Copy, Clone, Debug)]
+pub enum PropertyKind {
+    State,
+    OfflineMode,
+    SessionMode,
+}
+
+impl From<PropertyKind> for &'static str {
+    fn from(prop: PropertyKind) -> Self {
+        match prop {
+            PropertyKind::State => "State",
+            PropertyKind::OfflineMode => "OfflineMode",
+            PropertyKind::SessionMode => "SessionMode",
+        }
+    }
+}
+
+impl FromStr for PropertyKind {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "State" => Ok(PropertyKind::State),
+            "OfflineMode" => Ok(PropertyKind::OfflineMode),
+            "SessionMode" => Ok(PropertyKind::SessionMode),
+            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+        }
     }
 }
 
@@ -107,3 +551,12 @@ impl FromStr for State {
         }
     }
 }
+
+impl FromProperties for State {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        super::get_property_fromstr::<Self>(properties, prop_name)
+    }
+}
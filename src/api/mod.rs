@@ -3,16 +3,32 @@
 #[rustfmt::skip]
 mod gen;
 
+mod export;
+
+pub mod agent;
+pub mod clock;
+pub mod counter;
 pub mod manager;
+pub mod peer;
+pub mod private_network;
+pub mod provider;
+pub mod retry;
 pub mod service;
+pub mod service_id;
+pub mod service_list;
+pub mod session;
+pub mod signal;
 pub mod technology;
+pub mod vpn;
 
 use dbus;
-use dbus::arg::{cast, RefArg, Variant};
+use dbus::arg::{cast, ArgType, RefArg, Variant};
 use thiserror::Error;
 
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::net::Ipv4Addr;
 use std::str::FromStr;
 
 type RefArgMap = HashMap<String, Variant<Box<dyn RefArg + 'static>>>;
@@ -21,20 +37,277 @@ type RefArgIter<'a> = Box<dyn Iterator<Item = &'a dyn RefArg> + 'a>;
 
 #[derive(Debug, Error)]
 pub enum Error {
+    /// D-Bus error not raised by connman itself
     #[error("{0}")]
-    DbusError(#[from] dbus::Error),
+    DbusError(dbus::Error),
+    /// Error reply from connman (`net.connman.Error.*`)
+    #[error("{source}")]
+    Connman {
+        kind: ConnmanError,
+        source: dbus::Error,
+    },
     #[error("'{0}'")]
     PropertyError(#[from] PropertyError),
     #[error("Failed resolve before timeout: '{0}'")]
     Timeout(Cow<'static, str>),
+    #[error("Invalid argument: '{0}'")]
+    InvalidArgument(Cow<'static, str>),
+    #[error("Service failed: {0:?}")]
+    ServiceFailure(Option<service::Error>),
+    /// A signal stream being waited on ended, i.e. the connection is gone
+    #[error("Signal stream closed: '{0}'")]
+    SignalStreamClosed(Cow<'static, str>),
 }
 
-#[derive(Debug, Error)]
+impl Error {
+    /// The connman error, if this is an error reply from connman.
+    pub fn connman_error(&self) -> Option<&ConnmanError> {
+        match self {
+            Error::Connman { kind, .. } => Some(kind),
+            _ => None,
+        }
+    }
+}
+
+impl From<dbus::Error> for Error {
+    fn from(err: dbus::Error) -> Self {
+        match err.name().and_then(ConnmanError::from_name) {
+            Some(kind) => Error::Connman { kind, source: err },
+            None => Error::DbusError(err),
+        }
+    }
+}
+
+/// Errors connman and connman-vpnd reply with, by D-Bus error name.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConnmanError {
+    InvalidArguments,
+    PermissionDenied,
+    PassphraseRequired,
+    NotRegistered,
+    NotUnique,
+    NotSupported,
+    NotImplemented,
+    NotFound,
+    NoCarrier,
+    InProgress,
+    AlreadyExists,
+    AlreadyEnabled,
+    AlreadyDisabled,
+    AlreadyConnected,
+    NotConnected,
+    OperationAborted,
+    OperationTimeout,
+    OperationCanceled,
+    InvalidService,
+    InvalidProperty,
+    Failed,
+    Unknown(String),
+}
+
+impl ConnmanError {
+    /// Parse a D-Bus error name, returning `None` for names outside the
+    /// `net.connman.Error` and `net.connman.vpn.Error` namespaces.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name
+            .strip_prefix("net.connman.Error.")
+            .or_else(|| name.strip_prefix("net.connman.vpn.Error."))?;
+        Some(match name {
+            "InvalidArguments" => ConnmanError::InvalidArguments,
+            "PermissionDenied" => ConnmanError::PermissionDenied,
+            "PassphraseRequired" => ConnmanError::PassphraseRequired,
+            "NotRegistered" => ConnmanError::NotRegistered,
+            "NotUnique" => ConnmanError::NotUnique,
+            "NotSupported" => ConnmanError::NotSupported,
+            "NotImplemented" => ConnmanError::NotImplemented,
+            "NotFound" => ConnmanError::NotFound,
+            "NoCarrier" => ConnmanError::NoCarrier,
+            "InProgress" => ConnmanError::InProgress,
+            "AlreadyExists" => ConnmanError::AlreadyExists,
+            "AlreadyEnabled" => ConnmanError::AlreadyEnabled,
+            "AlreadyDisabled" => ConnmanError::AlreadyDisabled,
+            "AlreadyConnected" => ConnmanError::AlreadyConnected,
+            "NotConnected" => ConnmanError::NotConnected,
+            "OperationAborted" => ConnmanError::OperationAborted,
+            "OperationTimeout" => ConnmanError::OperationTimeout,
+            "OperationCanceled" => ConnmanError::OperationCanceled,
+            "InvalidService" => ConnmanError::InvalidService,
+            "InvalidProperty" => ConnmanError::InvalidProperty,
+            "Failed" => ConnmanError::Failed,
+            _ => ConnmanError::Unknown(name.to_string()),
+        })
+    }
+
+    /// Whether the same call may succeed if repeated later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ConnmanError::InProgress
+                | ConnmanError::NoCarrier
+                | ConnmanError::OperationAborted
+                | ConnmanError::OperationTimeout
+        )
+    }
+
+    /// Whether the call failed because of what was asked for, e.g. invalid
+    /// arguments or a request that doesn't fit the current state.
+    pub fn is_user_error(&self) -> bool {
+        matches!(
+            self,
+            ConnmanError::InvalidArguments
+                | ConnmanError::PermissionDenied
+                | ConnmanError::PassphraseRequired
+                | ConnmanError::NotFound
+                | ConnmanError::AlreadyExists
+                | ConnmanError::AlreadyEnabled
+                | ConnmanError::AlreadyDisabled
+                | ConnmanError::AlreadyConnected
+                | ConnmanError::NotConnected
+                | ConnmanError::OperationCanceled
+                | ConnmanError::InvalidService
+                | ConnmanError::InvalidProperty
+        )
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
 pub enum PropertyError {
     #[error("Property not present: '{0}'")]
     NotPresent(Cow<'static, str>),
     #[error("Failed to cast property: '{0}'")]
     Cast(Cow<'static, str>),
+    #[error("Unknown property value: '{0}'")]
+    UnknownValue(Cow<'static, str>),
+}
+
+/// How to handle property values that fail to parse.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ParseMode {
+    /// Any malformed or unrecognized value fails the whole object; only
+    /// open-ended values, like the service type, are let through as warnings
+    Strict,
+    /// Malformed values fall back to a default, and they and unrecognized
+    /// values are recorded as warnings
+    Lenient,
+}
+
+/// Owned D-Bus value, used to keep properties this crate doesn't know about.
+pub struct OwnedValue(pub Variant<Box<dyn RefArg + 'static>>);
+
+impl Clone for OwnedValue {
+    fn clone(&self) -> Self {
+        OwnedValue(Variant(self.0 .0.box_clone()))
+    }
+}
+
+impl fmt::Debug for OwnedValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl PartialEq for OwnedValue {
+    fn eq(&self, other: &Self) -> bool {
+        // `RefArg` has no equality; compare the signature and rendered value.
+        self.0.signature() == other.0.signature()
+            && format!("{:?}", self.0) == format!("{:?}", other.0)
+    }
+}
+
+/// Parses the properties of one object according to a `ParseMode`, collecting
+/// the warnings of lenient parsing.
+struct PropertyParser<'a> {
+    properties: &'a RefArgMap,
+    mode: ParseMode,
+    warnings: Vec<PropertyError>,
+}
+
+impl<'a> PropertyParser<'a> {
+    fn new(properties: &'a RefArgMap, mode: ParseMode) -> Self {
+        PropertyParser {
+            properties,
+            mode,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn get<T: FromProperties + Default>(
+        &mut self,
+        prop_name: &'static str,
+    ) -> Result<T, PropertyError> {
+        self.get_with(prop_name, T::from_properties, T::default)
+    }
+
+    /// Parses `prop_name` with `parse`, falling back to `default` in lenient
+    /// mode.
+    fn get_with<T>(
+        &mut self,
+        prop_name: &'static str,
+        parse: impl FnOnce(&RefArgMap, &'static str) -> Result<T, PropertyError>,
+        default: impl FnOnce() -> T,
+    ) -> Result<T, PropertyError> {
+        match parse(self.properties, prop_name) {
+            Err(e) if self.mode == ParseMode::Lenient => {
+                self.warnings.push(e);
+                Ok(default())
+            }
+            res => res,
+        }
+    }
+
+    /// Records a value that parsed, but isn't known to this crate.
+    fn unknown_value(&mut self, prop_name: &str, value: &str) {
+        self.warnings.push(unknown_value_error(prop_name, value));
+    }
+
+    /// Like `unknown_value`, but fails in strict mode, for values that are
+    /// expected to be one of a fixed set.
+    fn unrecognized_value(&mut self, prop_name: &str, value: &str) -> Result<(), PropertyError> {
+        match self.mode {
+            ParseMode::Strict => Err(unknown_value_error(prop_name, value)),
+            ParseMode::Lenient => {
+                self.unknown_value(prop_name, value);
+                Ok(())
+            }
+        }
+    }
+
+    fn into_warnings(self) -> Vec<PropertyError> {
+        self.warnings
+    }
+}
+
+fn unknown_value_error(prop_name: &str, value: &str) -> PropertyError {
+    PropertyError::UnknownValue(Cow::Owned(format!("{}: '{}'", prop_name, value)))
+}
+
+/// Deep-copies a property map, since `Variant<Box<dyn RefArg>>` is not `Clone`.
+fn clone_properties(properties: &RefArgMap) -> RefArgMap {
+    properties
+        .iter()
+        .map(|(k, v)| (k.clone(), Variant(v.0.box_clone())))
+        .collect()
+}
+
+/// Collects a string array, looking through any variant wrapping it.
+fn as_strings(arg: &dyn RefArg) -> Option<Vec<String>> {
+    if arg.arg_type() == ArgType::Variant {
+        return arg
+            .as_iter()
+            .and_then(|mut i| i.next())
+            .and_then(as_strings);
+    }
+    arg.as_iter()
+        .map(|i| i.filter_map(|s| s.as_str().map(String::from)).collect())
+}
+
+/// Collects a byte array, looking through any variant wrapping it.
+fn as_bytes(arg: &dyn RefArg) -> Option<Vec<u8>> {
+    if arg.arg_type() == ArgType::Variant {
+        return arg.as_iter().and_then(|mut i| i.next()).and_then(as_bytes);
+    }
+    arg.as_iter()
+        .map(|i| i.filter_map(|b| b.as_u64().map(|b| b as u8)).collect())
 }
 
 /// Convenience function for getting property values.
@@ -68,6 +341,26 @@ fn get_property_fromstr<T: FromStr + 'static>(
         })
 }
 
+/// Convenience function for getting string array property values whose
+/// elements impl `FromStr`.
+fn get_property_vec_fromstr<T: FromStr>(
+    properties: &RefArgMap,
+    prop_name: &'static str,
+) -> Result<Vec<T>, PropertyError> {
+    properties
+        .get(prop_name)
+        .ok_or(PropertyError::NotPresent(Cow::Borrowed(prop_name)))
+        .and_then(|variant| {
+            as_strings(&variant.0).ok_or(PropertyError::Cast(Cow::Borrowed(prop_name)))
+        })?
+        .iter()
+        .map(|s| {
+            T::from_str(s)
+                .map_err(|_| PropertyError::Cast(Cow::Owned(format!("{}: '{}'", prop_name, s))))
+        })
+        .collect()
+}
+
 /// Convenience function for getting property values from a Dict or Array.
 fn get_property_argiter<'a>(
     properties: &'a RefArgMap,
@@ -84,6 +377,30 @@ fn get_property_argiter<'a>(
         })
 }
 
+/// Inserts `value` under `prop_name`, wrapped in a variant.
+fn insert_property<T: RefArg + 'static>(properties: &mut RefArgMap, prop_name: &str, value: T) {
+    properties.insert(prop_name.to_string(), Variant(Box::new(value)));
+}
+
+/// Inserts `value` under `prop_name` if set.
+fn insert_property_opt<T: RefArg + 'static>(
+    properties: &mut RefArgMap,
+    prop_name: &str,
+    value: Option<T>,
+) {
+    if let Some(value) = value {
+        insert_property(properties, prop_name, value);
+    }
+}
+
+/// Inverse of `FromProperties`: serializes a value into the variant dict
+/// connman uses for it.
+///
+/// Parsing the result back yields a value equal to the original.
+pub trait IntoProperties {
+    fn into_properties(self) -> RefArgMap;
+}
+
 pub trait FromProperties: Sized {
     fn from_properties(
         properties: &RefArgMap,
@@ -118,6 +435,42 @@ impl FromProperties for u8 {
     }
 }
 
+impl FromProperties for i32 {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        get_property::<Self>(properties, prop_name)
+    }
+}
+
+impl FromProperties for u32 {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        get_property::<Self>(properties, prop_name)
+    }
+}
+
+impl FromProperties for u64 {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        get_property::<Self>(properties, prop_name)
+    }
+}
+
+impl FromProperties for Ipv4Addr {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        get_property_fromstr::<Self>(properties, prop_name)
+    }
+}
+
 impl FromProperties for bool {
     fn from_properties(
         properties: &RefArgMap,
@@ -139,3 +492,125 @@ impl<T: FromProperties> FromProperties for Option<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connman_error_known_name() {
+        assert_eq!(
+            ConnmanError::from_name("net.connman.Error.AlreadyConnected"),
+            Some(ConnmanError::AlreadyConnected)
+        );
+    }
+
+    #[test]
+    fn connman_error_unknown_name() {
+        assert_eq!(
+            ConnmanError::from_name("net.connman.Error.SomethingNew"),
+            Some(ConnmanError::Unknown("SomethingNew".to_string()))
+        );
+    }
+
+    #[test]
+    fn connman_error_vpn_namespace() {
+        assert_eq!(
+            ConnmanError::from_name("net.connman.vpn.Error.InProgress"),
+            Some(ConnmanError::InProgress)
+        );
+    }
+
+    #[test]
+    fn connman_error_foreign_name() {
+        assert_eq!(
+            ConnmanError::from_name("org.freedesktop.DBus.Error.NoReply"),
+            None
+        );
+        assert_eq!(
+            ConnmanError::from_name("net.connman.Agent.Error.Retry"),
+            None
+        );
+    }
+
+    #[test]
+    fn error_from_dbus() {
+        let err = Error::from(dbus::Error::new_custom(
+            "net.connman.Error.NoCarrier",
+            "No carrier",
+        ));
+        assert_eq!(err.connman_error(), Some(&ConnmanError::NoCarrier));
+
+        let err = Error::from(dbus::Error::new_custom(
+            "org.freedesktop.DBus.Error.NoReply",
+            "No reply",
+        ));
+        assert!(matches!(err, Error::DbusError(_)));
+    }
+
+    fn counter(value: &str) -> RefArgMap {
+        let mut props = RefArgMap::new();
+        insert_property(&mut props, "Count", value.to_string());
+        props
+    }
+
+    #[test]
+    fn parser_lenient_collects_warnings() {
+        let props = counter("many");
+        let mut p = PropertyParser::new(&props, ParseMode::Lenient);
+        assert_eq!(p.get::<u32>("Count"), Ok(0));
+        assert_eq!(p.unrecognized_value("State", "waiting"), Ok(()));
+
+        let warnings = p.into_warnings();
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(warnings[0], PropertyError::Cast(_)));
+        assert!(matches!(warnings[1], PropertyError::UnknownValue(_)));
+    }
+
+    #[test]
+    fn parser_strict_fails() {
+        let props = counter("many");
+        let mut p = PropertyParser::new(&props, ParseMode::Strict);
+        assert!(matches!(p.get::<u32>("Count"), Err(PropertyError::Cast(_))));
+        assert!(matches!(
+            p.unrecognized_value("State", "waiting"),
+            Err(PropertyError::UnknownValue(_))
+        ));
+        assert!(p.into_warnings().is_empty());
+    }
+
+    /// A service property map with an unknown key, a malformed nameserver and
+    /// an unknown state.
+    fn odd_service() -> RefArgMap {
+        let mut props = service::Properties::parse(RefArgMap::new(), ParseMode::Lenient)
+            .unwrap()
+            .into_properties();
+        insert_property(&mut props, "Future", 7u32);
+        insert_property(&mut props, "Nameservers", vec!["not-an-ip".to_string()]);
+        insert_property(&mut props, "State", "waiting".to_string());
+        props
+    }
+
+    #[test]
+    fn service_lenient_collects_extra_and_warnings() {
+        let parsed = service::Properties::parse(odd_service(), ParseMode::Lenient).unwrap();
+        assert!(parsed.extra.contains_key("Future"));
+        assert!(parsed.nameservers.is_empty());
+        assert_eq!(parsed.state, service::State::Unknown("waiting".to_string()));
+        assert_eq!(parsed.warnings.len(), 2);
+        assert!(matches!(parsed.warnings[0], PropertyError::Cast(_)));
+        assert!(matches!(parsed.warnings[1], PropertyError::UnknownValue(_)));
+    }
+
+    #[test]
+    fn service_strict_fails() {
+        assert!(service::Properties::parse(odd_service(), ParseMode::Strict).is_err());
+
+        let mut props = odd_service();
+        insert_property(&mut props, "Nameservers", vec!["1.1.1.1".to_string()]);
+        assert!(matches!(
+            service::Properties::parse(props, ParseMode::Strict),
+            Err(PropertyError::UnknownValue(_))
+        ));
+    }
+}
@@ -0,0 +1,388 @@
+use dbus::arg::{RefArg, Variant};
+use dbus::nonblock::{NonblockReply, Proxy as DBusProxy, SyncConnection};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+
+#[cfg(feature = "introspection")]
+use xml::reader::EventReader;
+
+use super::export::call_no_reply;
+use super::gen::manager::Manager as IManager;
+use super::gen::peer::Peer as IPeer;
+use super::service::State;
+use super::Error as ApiError;
+use super::{get_property_argiter, FromProperties, PropertyError, RefArgMap};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Futures-aware wrapper struct for connman Peer object.
+#[derive(Clone)]
+pub struct Peer<C> {
+    proxy: DBusProxy<'static, C>,
+    pub props: Properties,
+}
+
+impl<C> Peer<C> {
+    pub fn new(
+        connection: C,
+        path: dbus::Path<'static>,
+        args: RefArgMap,
+        timeout: Duration,
+    ) -> Result<Self, ApiError> {
+        Ok(Self::try_new(connection, path, args, timeout)?)
+    }
+
+    pub(crate) fn try_new(
+        connection: C,
+        path: dbus::Path<'static>,
+        args: RefArgMap,
+        timeout: Duration,
+    ) -> Result<Self, PropertyError> {
+        let properties = Properties::try_from(args)?;
+
+        Ok(Peer {
+            proxy: Self::proxy(path, timeout, connection),
+            props: properties,
+        })
+    }
+
+    pub fn proxy(path: dbus::Path<'static>, timeout: Duration, conn: C) -> DBusProxy<'static, C> {
+        let proxy = DBusProxy::new("net.connman", path, timeout, conn);
+        proxy
+    }
+
+    pub fn path(&self) -> &dbus::Path<'static> {
+        &self.proxy.path
+    }
+}
+
+impl<T: NonblockReply, C: Deref<Target = T>> Peer<C> {
+    #[cfg(feature = "introspection")]
+    pub async fn introspect(&self) -> Result<EventReader<std::io::Cursor<Vec<u8>>>, ApiError> {
+        use crate::api::gen::peer::OrgFreedesktopDBusIntrospectable as Introspectable;
+
+        let s = Introspectable::introspect(&self.proxy).await?;
+        let rdr = std::io::Cursor::new(s.into_bytes());
+        Ok(EventReader::new(rdr))
+    }
+
+    pub async fn connect(&self) -> Result<(), ApiError> {
+        Ok(IPeer::connect(&self.proxy).await?)
+    }
+
+    pub async fn disconnect(&self) -> Result<(), ApiError> {
+        Ok(IPeer::disconnect(&self.proxy).await?)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Properties {
+    /// Connection state
+    pub state: State,
+    /// Peer name
+    pub name: String,
+    /// Ipv4 related information; only set once connected
+    pub ipv4: Option<Ipv4>,
+    /// P2P services advertised by the peer
+    pub services: Vec<PeerService>,
+}
+
+impl Properties {
+    pub fn try_from(props: RefArgMap) -> Result<Self, PropertyError> {
+        let state = State::from_properties(&props, PropertyKind::State.into())?;
+        let name = String::from_properties(&props, PropertyKind::Name.into())?;
+        let ipv4: Option<Ipv4> =
+            FromProperties::from_properties(&props, PropertyKind::Ipv4.into())?;
+        let services: Option<Vec<PeerService>> =
+            FromProperties::from_properties(&props, PropertyKind::Services.into())?;
+
+        Ok(Properties {
+            state,
+            name,
+            ipv4,
+            services: services.unwrap_or_default(),
+        })
+    }
+}
+
+/// Peer property fields.
+#[derive(Copy, Clone, Debug)]
+pub enum PropertyKind {
+    State,
+    Name,
+    Ipv4,
+    Services,
+}
+
+impl From<PropertyKind> for &'static str {
+    fn from(prop: PropertyKind) -> Self {
+        match prop {
+            PropertyKind::State => "State",
+            PropertyKind::Name => "Name",
+            PropertyKind::Ipv4 => "IPv4",
+            PropertyKind::Services => "Services",
+        }
+    }
+}
+
+impl FromStr for PropertyKind {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "State" => Ok(PropertyKind::State),
+            "Name" => Ok(PropertyKind::Name),
+            "IPv4" => Ok(PropertyKind::Ipv4),
+            "Services" => Ok(PropertyKind::Services),
+            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+        }
+    }
+}
+
+/// Peer Ipv4 structure
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ipv4 {
+    /// Local address on the P2P link
+    pub local: Option<Ipv4Addr>,
+    /// Address of the peer
+    pub remote: Option<Ipv4Addr>,
+}
+
+pub enum Ipv4Kind {
+    Local,
+    Remote,
+}
+
+impl From<Ipv4Kind> for &'static str {
+    fn from(prop: Ipv4Kind) -> Self {
+        match prop {
+            Ipv4Kind::Local => "Local",
+            Ipv4Kind::Remote => "Remote",
+        }
+    }
+}
+
+impl FromProperties for Ipv4 {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        let mut i = get_property_argiter(properties, prop_name)?;
+        let mut m: HashMap<&str, &str> = HashMap::new();
+        while let Some(key) = i.next().and_then(|k| k.as_str()) {
+            if let Some(val) = i.next().and_then(|v| v.as_str()) {
+                let _ = m.insert(key, val);
+            }
+        }
+
+        let addr = |kind: Ipv4Kind| -> Result<Option<Ipv4Addr>, PropertyError> {
+            let key: &'static str = kind.into();
+            m.get(key)
+                .map(|addr| {
+                    addr.parse().map_err(|_| {
+                        PropertyError::Cast(Cow::Owned(format!(
+                            "{}.{}: '{}'",
+                            prop_name, key, addr
+                        )))
+                    })
+                })
+                .transpose()
+        };
+        let local = addr(Ipv4Kind::Local)?;
+        let remote = addr(Ipv4Kind::Remote)?;
+
+        Ok(Ipv4 { local, remote })
+    }
+}
+
+/// Service advertised by a peer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PeerService {
+    /// Wi-Fi Display information elements
+    pub wifi_display_ies: Option<Vec<u8>>,
+}
+
+pub enum PeerServiceKind {
+    WiFiDisplayIEs,
+}
+
+impl From<PeerServiceKind> for &'static str {
+    fn from(prop: PeerServiceKind) -> Self {
+        match prop {
+            PeerServiceKind::WiFiDisplayIEs => "WiFiDisplayIEs",
+        }
+    }
+}
+
+impl FromProperties for Vec<PeerService> {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        let services = get_property_argiter(properties, prop_name)?;
+
+        services
+            .map(|service| {
+                let mut i = service
+                    .as_iter()
+                    .ok_or(PropertyError::Cast(Cow::Borrowed(prop_name)))?;
+                let mut wifi_display_ies = None;
+                while let Some(key) = i.next().and_then(|k| k.as_str()) {
+                    let val = i.next();
+                    if key == <&str>::from(PeerServiceKind::WiFiDisplayIEs) {
+                        wifi_display_ies = val.and_then(super::as_bytes);
+                    }
+                }
+                Ok(PeerService { wifi_display_ies })
+            })
+            .collect()
+    }
+}
+
+/// Specification of a P2P service to advertise with
+/// `Manager::register_peer_service`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PeerServiceSpec {
+    /// Bonjour service, given as its DNS-SD query and response records
+    Bonjour { query: Vec<u8>, response: Vec<u8> },
+    /// UPnP service, given as its USN and UPnP version
+    Upnp { service: String, version: i32 },
+    /// Wi-Fi Display information elements
+    WiFiDisplay { ies: Vec<u8> },
+}
+
+impl PeerServiceSpec {
+    /// Checks the specification before it is sent to connman.
+    pub fn validate(&self) -> Result<(), ApiError> {
+        let invalid = |field: &'static str| Err(ApiError::InvalidArgument(Cow::Borrowed(field)));
+
+        match self {
+            PeerServiceSpec::Bonjour { query, .. } if query.is_empty() => {
+                invalid(PeerServiceSpecKind::BonjourQuery.into())
+            }
+            PeerServiceSpec::Bonjour { response, .. } if response.is_empty() => {
+                invalid(PeerServiceSpecKind::BonjourResponse.into())
+            }
+            PeerServiceSpec::Upnp { service, .. } if service.is_empty() => {
+                invalid(PeerServiceSpecKind::UpnpService.into())
+            }
+            PeerServiceSpec::Upnp { version, .. } if *version < 1 => {
+                invalid(PeerServiceSpecKind::UpnpVersion.into())
+            }
+            PeerServiceSpec::WiFiDisplay { ies } if ies.is_empty() => {
+                invalid(PeerServiceSpecKind::WiFiDisplayIEs.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn to_dict(&self) -> HashMap<&'static str, Variant<Box<dyn RefArg>>> {
+        let mut dict: HashMap<&'static str, Variant<Box<dyn RefArg>>> = HashMap::new();
+        match self.clone() {
+            PeerServiceSpec::Bonjour { query, response } => {
+                dict.insert(
+                    PeerServiceSpecKind::BonjourQuery.into(),
+                    Variant(Box::new(query)),
+                );
+                dict.insert(
+                    PeerServiceSpecKind::BonjourResponse.into(),
+                    Variant(Box::new(response)),
+                );
+            }
+            PeerServiceSpec::Upnp { service, version } => {
+                dict.insert(
+                    PeerServiceSpecKind::UpnpService.into(),
+                    Variant(Box::new(service)),
+                );
+                dict.insert(
+                    PeerServiceSpecKind::UpnpVersion.into(),
+                    Variant(Box::new(version)),
+                );
+            }
+            PeerServiceSpec::WiFiDisplay { ies } => {
+                dict.insert(
+                    PeerServiceSpecKind::WiFiDisplayIEs.into(),
+                    Variant(Box::new(ies)),
+                );
+            }
+        }
+        dict
+    }
+}
+
+/// Peer service specification fields.
+pub enum PeerServiceSpecKind {
+    BonjourQuery,
+    BonjourResponse,
+    UpnpService,
+    UpnpVersion,
+    WiFiDisplayIEs,
+}
+
+impl From<PeerServiceSpecKind> for &'static str {
+    fn from(prop: PeerServiceSpecKind) -> Self {
+        match prop {
+            PeerServiceSpecKind::BonjourQuery => "BonjourQuery",
+            PeerServiceSpecKind::BonjourResponse => "BonjourResponse",
+            PeerServiceSpecKind::UpnpService => "UpnpService",
+            PeerServiceSpecKind::UpnpVersion => "UpnpVersion",
+            PeerServiceSpecKind::WiFiDisplayIEs => "WiFiDisplayIEs",
+        }
+    }
+}
+
+/// Peer service registered with the connman Manager.
+///
+/// The service is unregistered when this is dropped; use `unregister` to wait
+/// for connman to acknowledge it instead.
+pub struct PeerServiceRegistration {
+    spec: PeerServiceSpec,
+    manager: Option<DBusProxy<'static, Arc<SyncConnection>>>,
+}
+
+impl PeerServiceRegistration {
+    pub(crate) async fn new(
+        manager: DBusProxy<'static, Arc<SyncConnection>>,
+        spec: PeerServiceSpec,
+        master: bool,
+    ) -> Result<Self, ApiError> {
+        spec.validate()?;
+        IManager::register_peer_service(&manager, spec.to_dict(), master).await?;
+
+        Ok(PeerServiceRegistration {
+            spec,
+            manager: Some(manager),
+        })
+    }
+
+    pub fn spec(&self) -> &PeerServiceSpec {
+        &self.spec
+    }
+
+    pub async fn unregister(mut self) -> Result<(), ApiError> {
+        match self.manager.take() {
+            Some(manager) => {
+                Ok(IManager::unregister_peer_service(&manager, self.spec.to_dict()).await?)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for PeerServiceRegistration {
+    fn drop(&mut self) {
+        if let Some(manager) = self.manager.take() {
+            call_no_reply(
+                &manager.connection,
+                "net.connman",
+                "/".into(),
+                "net.connman.Manager",
+                "UnregisterPeerService",
+                (self.spec.to_dict(),),
+            );
+        }
+    }
+}
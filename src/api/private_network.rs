@@ -0,0 +1,131 @@
+//! Private networks handed out to sandboxed workloads
+
+use dbus::arg::OwnedFd;
+use dbus::nonblock::{Proxy, SyncConnection};
+
+use super::export::call_no_reply;
+use super::gen::manager::Manager as IManager;
+use super::{Error, FromProperties, PropertyError, RefArgMap};
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+
+/// Private network requested from the connman Manager.
+///
+/// Holds the TUN device backing the network; the network is released when
+/// this is dropped.
+pub struct PrivateNetwork {
+    path: dbus::Path<'static>,
+    fd: OwnedFd,
+    pub settings: Settings,
+    manager: Option<Proxy<'static, Arc<SyncConnection>>>,
+}
+
+impl PrivateNetwork {
+    pub(crate) async fn new(manager: Proxy<'static, Arc<SyncConnection>>) -> Result<Self, Error> {
+        let (path, props, fd) = IManager::request_private_network(&manager).await?;
+
+        let mut network = PrivateNetwork {
+            path,
+            fd,
+            settings: Settings::default(),
+            manager: Some(manager),
+        };
+        // Parsed after construction so the network is still released on error.
+        network.settings = Settings::try_from(props)?;
+        Ok(network)
+    }
+
+    pub fn path(&self) -> &dbus::Path<'static> {
+        &self.path
+    }
+
+    /// File descriptor of the TUN device
+    pub fn fd(&self) -> &OwnedFd {
+        &self.fd
+    }
+
+    pub async fn release(mut self) -> Result<(), Error> {
+        match self.manager.take() {
+            Some(manager) => {
+                Ok(IManager::release_private_network(&manager, self.path.clone()).await?)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for PrivateNetwork {
+    fn drop(&mut self) {
+        if let Some(manager) = self.manager.take() {
+            call_no_reply(
+                &manager.connection,
+                "net.connman",
+                "/".into(),
+                "net.connman.Manager",
+                "ReleasePrivateNetwork",
+                (self.path.clone(),),
+            );
+        }
+    }
+}
+
+/// Addressing of a private network.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Settings {
+    /// Address of the TUN device
+    pub address: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+    /// Address of connman's end of the link
+    pub peer_address: Ipv4Addr,
+    pub primary_dns: Ipv4Addr,
+    pub secondary_dns: Option<Ipv4Addr>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            address: Ipv4Addr::UNSPECIFIED,
+            netmask: Ipv4Addr::UNSPECIFIED,
+            peer_address: Ipv4Addr::UNSPECIFIED,
+            primary_dns: Ipv4Addr::UNSPECIFIED,
+            secondary_dns: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn try_from(props: RefArgMap) -> Result<Self, PropertyError> {
+        Ok(Settings {
+            address: Ipv4Addr::from_properties(&props, SettingsKind::Address.into())?,
+            netmask: Ipv4Addr::from_properties(&props, SettingsKind::Netmask.into())?,
+            peer_address: Ipv4Addr::from_properties(&props, SettingsKind::PeerAddress.into())?,
+            primary_dns: Ipv4Addr::from_properties(&props, SettingsKind::PrimaryDns.into())?,
+            secondary_dns: FromProperties::from_properties(
+                &props,
+                SettingsKind::SecondaryDns.into(),
+            )?,
+        })
+    }
+}
+
+/// Private network setting fields.
+#[derive(Copy, Clone, Debug)]
+pub enum SettingsKind {
+    Address,
+    Netmask,
+    PeerAddress,
+    PrimaryDns,
+    SecondaryDns,
+}
+
+impl From<SettingsKind> for &'static str {
+    fn from(kind: SettingsKind) -> Self {
+        match kind {
+            SettingsKind::Address => "Address",
+            SettingsKind::Netmask => "Netmask",
+            SettingsKind::PeerAddress => "PeerAddress",
+            SettingsKind::PrimaryDns => "PrimaryDNS",
+            SettingsKind::SecondaryDns => "SecondaryDNS",
+        }
+    }
+}
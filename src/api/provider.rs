@@ -0,0 +1,753 @@
+//! Typed settings for `Manager::connect_provider`
+
+use dbus::arg::{RefArg, Variant};
+
+use super::service::ProviderKind;
+use super::{Error, PropertyError};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Settings for a VPN provider created with `Manager::connect_provider`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProviderSettings {
+    name: String,
+    host: String,
+    domain: String,
+    networks: Vec<String>,
+    vpn: VpnSettings,
+}
+
+impl ProviderSettings {
+    /// `host` is the VPN server, `domain` the domain the VPN belongs to.
+    pub fn new(
+        name: impl Into<String>,
+        host: impl Into<String>,
+        domain: impl Into<String>,
+        vpn: impl Into<VpnSettings>,
+    ) -> Self {
+        ProviderSettings {
+            name: name.into(),
+            host: host.into(),
+            domain: domain.into(),
+            networks: Vec::new(),
+            vpn: vpn.into(),
+        }
+    }
+
+    /// Route only `network` (e.g. `"10.0.0.0/255.255.0.0/10.0.0.1"`) through
+    /// the VPN; may be given several times.
+    pub fn network(mut self, network: impl Into<String>) -> Self {
+        self.networks.push(network.into());
+        self
+    }
+
+    pub fn vpn_type(&self) -> VpnType {
+        self.vpn.vpn_type()
+    }
+
+    /// Checks the settings before they are sent to connman.
+    pub fn validate(&self) -> Result<(), Error> {
+        require(&self.name, ProviderKind::Name.into())?;
+        require(&self.host, ProviderKind::Host.into())?;
+        require(&self.domain, ProviderKind::Domain.into())?;
+        self.vpn.validate()
+    }
+
+    pub(crate) fn to_dict(&self) -> HashMap<&'static str, Variant<Box<dyn RefArg>>> {
+        let mut dict: HashMap<&'static str, Variant<Box<dyn RefArg>>> = HashMap::new();
+        let vpn_type: &'static str = self.vpn_type().into();
+        dict.insert(
+            ProviderKind::Type.into(),
+            Variant(Box::new(vpn_type.to_string())),
+        );
+        dict.insert(
+            ProviderKind::Name.into(),
+            Variant(Box::new(self.name.clone())),
+        );
+        dict.insert(
+            ProviderKind::Host.into(),
+            Variant(Box::new(self.host.clone())),
+        );
+        dict.insert(
+            ProviderKind::Domain.into(),
+            Variant(Box::new(self.domain.clone())),
+        );
+        if !self.networks.is_empty() {
+            dict.insert(
+                ProviderSettingKind::Networks.into(),
+                Variant(Box::new(self.networks.join(","))),
+            );
+        }
+
+        for (kind, value) in self.vpn.options() {
+            dict.insert(kind.into(), Variant(Box::new(value)));
+        }
+        dict
+    }
+}
+
+fn require(value: &str, field: &'static str) -> Result<(), Error> {
+    if value.is_empty() {
+        Err(Error::InvalidArgument(Cow::Borrowed(field)))
+    } else {
+        Ok(())
+    }
+}
+
+/// VPN type specific settings.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VpnSettings {
+    OpenVpn(OpenVpn),
+    OpenConnect(OpenConnect),
+    Vpnc(Vpnc),
+    L2tp(L2tp),
+    Pptp(Pptp),
+    WireGuard(WireGuard),
+}
+
+impl VpnSettings {
+    pub fn vpn_type(&self) -> VpnType {
+        match self {
+            VpnSettings::OpenVpn(_) => VpnType::OpenVpn,
+            VpnSettings::OpenConnect(_) => VpnType::OpenConnect,
+            VpnSettings::Vpnc(_) => VpnType::Vpnc,
+            VpnSettings::L2tp(_) => VpnType::L2tp,
+            VpnSettings::Pptp(_) => VpnType::Pptp,
+            VpnSettings::WireGuard(_) => VpnType::WireGuard,
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        match self {
+            VpnSettings::Vpnc(v) => require(&v.group, ProviderSettingKind::VpncIpsecId.into()),
+            VpnSettings::WireGuard(v) => {
+                require(&v.address, ProviderSettingKind::WireGuardAddress.into())?;
+                require(
+                    &v.private_key,
+                    ProviderSettingKind::WireGuardPrivateKey.into(),
+                )?;
+                require(
+                    &v.public_key,
+                    ProviderSettingKind::WireGuardPublicKey.into(),
+                )?;
+                if v.allowed_ips.is_empty() {
+                    return Err(Error::InvalidArgument(Cow::Borrowed(
+                        ProviderSettingKind::WireGuardAllowedIps.into(),
+                    )));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Type specific entries of the provider dict.
+    fn options(&self) -> Vec<(ProviderSettingKind, String)> {
+        use ProviderSettingKind::*;
+
+        let mut opts = Vec::new();
+        let mut push = |kind: ProviderSettingKind, value: &Option<String>| {
+            if let Some(value) = value {
+                opts.push((kind, value.clone()));
+            }
+        };
+
+        match self {
+            VpnSettings::OpenVpn(v) => {
+                push(OpenVpnCaCert, &v.ca_cert);
+                push(OpenVpnCert, &v.cert);
+                push(OpenVpnKey, &v.key);
+                push(OpenVpnProto, &v.proto);
+                push(OpenVpnPort, &v.port.map(|p| p.to_string()));
+                push(OpenVpnConfigFile, &v.config_file);
+            }
+            VpnSettings::OpenConnect(v) => {
+                push(OpenConnectProtocol, &v.protocol);
+                push(OpenConnectServerCert, &v.server_cert);
+                push(OpenConnectCaCert, &v.ca_cert);
+                push(OpenConnectUsergroup, &v.usergroup);
+            }
+            VpnSettings::Vpnc(v) => {
+                push(VpncIpsecId, &Some(v.group.clone()));
+                push(VpncIpsecSecret, &v.group_secret);
+                push(VpncXauthUsername, &v.username);
+                push(VpncXauthPassword, &v.password);
+            }
+            VpnSettings::L2tp(v) => {
+                push(L2tpUser, &v.user);
+                push(L2tpPassword, &v.password);
+            }
+            VpnSettings::Pptp(v) => {
+                push(PptpUser, &v.user);
+                push(PptpPassword, &v.password);
+            }
+            VpnSettings::WireGuard(v) => {
+                push(WireGuardAddress, &Some(v.address.clone()));
+                push(WireGuardPrivateKey, &Some(v.private_key.clone()));
+                push(WireGuardPublicKey, &Some(v.public_key.clone()));
+                push(WireGuardAllowedIps, &Some(v.allowed_ips.join(",")));
+                push(WireGuardPresharedKey, &v.preshared_key);
+                push(
+                    WireGuardEndpointPort,
+                    &v.endpoint_port.map(|p| p.to_string()),
+                );
+                push(
+                    WireGuardPersistentKeepalive,
+                    &v.persistent_keepalive.map(|k| k.to_string()),
+                );
+            }
+        }
+        opts
+    }
+}
+
+/// OpenVPN settings; all fields are optional.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OpenVpn {
+    ca_cert: Option<String>,
+    cert: Option<String>,
+    key: Option<String>,
+    proto: Option<String>,
+    port: Option<u16>,
+    config_file: Option<String>,
+}
+
+impl OpenVpn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Path to the CA certificate file
+    pub fn ca_cert(mut self, path: impl Into<String>) -> Self {
+        self.ca_cert = Some(path.into());
+        self
+    }
+
+    /// Path to the client certificate file
+    pub fn cert(mut self, path: impl Into<String>) -> Self {
+        self.cert = Some(path.into());
+        self
+    }
+
+    /// Path to the client private key file
+    pub fn key(mut self, path: impl Into<String>) -> Self {
+        self.key = Some(path.into());
+        self
+    }
+
+    /// Transport protocol, `"udp"` or `"tcp-client"`
+    pub fn proto(mut self, proto: impl Into<String>) -> Self {
+        self.proto = Some(proto.into());
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Path to an OpenVPN config file with further options
+    pub fn config_file(mut self, path: impl Into<String>) -> Self {
+        self.config_file = Some(path.into());
+        self
+    }
+}
+
+impl From<OpenVpn> for VpnSettings {
+    fn from(settings: OpenVpn) -> Self {
+        VpnSettings::OpenVpn(settings)
+    }
+}
+
+/// OpenConnect settings; all fields are optional.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OpenConnect {
+    protocol: Option<String>,
+    server_cert: Option<String>,
+    ca_cert: Option<String>,
+    usergroup: Option<String>,
+}
+
+impl OpenConnect {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// VPN protocol, e.g. `"anyconnect"`, `"nc"` or `"gp"`
+    pub fn protocol(mut self, protocol: impl Into<String>) -> Self {
+        self.protocol = Some(protocol.into());
+        self
+    }
+
+    /// SHA1 fingerprint of the server certificate
+    pub fn server_cert(mut self, fingerprint: impl Into<String>) -> Self {
+        self.server_cert = Some(fingerprint.into());
+        self
+    }
+
+    /// Path to the CA certificate file
+    pub fn ca_cert(mut self, path: impl Into<String>) -> Self {
+        self.ca_cert = Some(path.into());
+        self
+    }
+
+    pub fn usergroup(mut self, usergroup: impl Into<String>) -> Self {
+        self.usergroup = Some(usergroup.into());
+        self
+    }
+}
+
+impl From<OpenConnect> for VpnSettings {
+    fn from(settings: OpenConnect) -> Self {
+        VpnSettings::OpenConnect(settings)
+    }
+}
+
+/// vpnc (Cisco IPsec) settings.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Vpnc {
+    group: String,
+    group_secret: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl Vpnc {
+    /// `group` is the IPsec group name.
+    pub fn new(group: impl Into<String>) -> Self {
+        Vpnc {
+            group: group.into(),
+            group_secret: None,
+            username: None,
+            password: None,
+        }
+    }
+
+    /// IPsec group secret; requested through the VPN agent if unset
+    pub fn group_secret(mut self, secret: impl Into<String>) -> Self {
+        self.group_secret = Some(secret.into());
+        self
+    }
+
+    pub fn username(mut self, username: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+}
+
+impl From<Vpnc> for VpnSettings {
+    fn from(settings: Vpnc) -> Self {
+        VpnSettings::Vpnc(settings)
+    }
+}
+
+/// L2TP settings; credentials are requested through the VPN agent if unset.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct L2tp {
+    user: Option<String>,
+    password: Option<String>,
+}
+
+impl L2tp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+}
+
+impl From<L2tp> for VpnSettings {
+    fn from(settings: L2tp) -> Self {
+        VpnSettings::L2tp(settings)
+    }
+}
+
+/// PPTP settings; credentials are requested through the VPN agent if unset.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Pptp {
+    user: Option<String>,
+    password: Option<String>,
+}
+
+impl Pptp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+}
+
+impl From<Pptp> for VpnSettings {
+    fn from(settings: Pptp) -> Self {
+        VpnSettings::Pptp(settings)
+    }
+}
+
+/// WireGuard settings.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WireGuard {
+    address: String,
+    private_key: String,
+    public_key: String,
+    allowed_ips: Vec<String>,
+    preshared_key: Option<String>,
+    endpoint_port: Option<u16>,
+    persistent_keepalive: Option<u32>,
+}
+
+impl WireGuard {
+    /// `address` is the local tunnel address, `public_key` the key of the
+    /// peer.
+    pub fn new(
+        address: impl Into<String>,
+        private_key: impl Into<String>,
+        public_key: impl Into<String>,
+    ) -> Self {
+        WireGuard {
+            address: address.into(),
+            private_key: private_key.into(),
+            public_key: public_key.into(),
+            allowed_ips: Vec::new(),
+            preshared_key: None,
+            endpoint_port: None,
+            persistent_keepalive: None,
+        }
+    }
+
+    /// Network routed through the tunnel, e.g. `"0.0.0.0/0"`; at least one
+    /// is required.
+    pub fn allowed_ip(mut self, network: impl Into<String>) -> Self {
+        self.allowed_ips.push(network.into());
+        self
+    }
+
+    pub fn preshared_key(mut self, key: impl Into<String>) -> Self {
+        self.preshared_key = Some(key.into());
+        self
+    }
+
+    pub fn endpoint_port(mut self, port: u16) -> Self {
+        self.endpoint_port = Some(port);
+        self
+    }
+
+    /// Keepalive interval, in seconds
+    pub fn persistent_keepalive(mut self, secs: u32) -> Self {
+        self.persistent_keepalive = Some(secs);
+        self
+    }
+}
+
+impl From<WireGuard> for VpnSettings {
+    fn from(settings: WireGuard) -> Self {
+        VpnSettings::WireGuard(settings)
+    }
+}
+
+/// VPN types supported by connman.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum VpnType {
+    OpenVpn,
+    OpenConnect,
+    Vpnc,
+    L2tp,
+    Pptp,
+    WireGuard,
+}
+
+impl FromStr for VpnType {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "openvpn" => Ok(VpnType::OpenVpn),
+            "openconnect" => Ok(VpnType::OpenConnect),
+            "vpnc" => Ok(VpnType::Vpnc),
+            "l2tp" => Ok(VpnType::L2tp),
+            "pptp" => Ok(VpnType::Pptp),
+            "wireguard" => Ok(VpnType::WireGuard),
+            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+        }
+    }
+}
+
+impl From<VpnType> for &'static str {
+    fn from(ty: VpnType) -> Self {
+        match ty {
+            VpnType::OpenVpn => "openvpn",
+            VpnType::OpenConnect => "openconnect",
+            VpnType::Vpnc => "vpnc",
+            VpnType::L2tp => "l2tp",
+            VpnType::Pptp => "pptp",
+            VpnType::WireGuard => "wireguard",
+        }
+    }
+}
+
+/// Provider dict fields besides the common `ProviderKind` ones.
+#[derive(Copy, Clone, Debug)]
+pub enum ProviderSettingKind {
+    Networks,
+    OpenVpnCaCert,
+    OpenVpnCert,
+    OpenVpnKey,
+    OpenVpnProto,
+    OpenVpnPort,
+    OpenVpnConfigFile,
+    OpenConnectProtocol,
+    OpenConnectServerCert,
+    OpenConnectCaCert,
+    OpenConnectUsergroup,
+    VpncIpsecId,
+    VpncIpsecSecret,
+    VpncXauthUsername,
+    VpncXauthPassword,
+    L2tpUser,
+    L2tpPassword,
+    PptpUser,
+    PptpPassword,
+    WireGuardAddress,
+    WireGuardPrivateKey,
+    WireGuardPublicKey,
+    WireGuardAllowedIps,
+    WireGuardPresharedKey,
+    WireGuardEndpointPort,
+    WireGuardPersistentKeepalive,
+}
+
+impl From<ProviderSettingKind> for &'static str {
+    fn from(kind: ProviderSettingKind) -> Self {
+        match kind {
+            ProviderSettingKind::Networks => "Networks",
+            ProviderSettingKind::OpenVpnCaCert => "OpenVPN.CACert",
+            ProviderSettingKind::OpenVpnCert => "OpenVPN.Cert",
+            ProviderSettingKind::OpenVpnKey => "OpenVPN.Key",
+            ProviderSettingKind::OpenVpnProto => "OpenVPN.Proto",
+            ProviderSettingKind::OpenVpnPort => "OpenVPN.Port",
+            ProviderSettingKind::OpenVpnConfigFile => "OpenVPN.ConfigFile",
+            ProviderSettingKind::OpenConnectProtocol => "OpenConnect.Protocol",
+            ProviderSettingKind::OpenConnectServerCert => "OpenConnect.ServerCert",
+            ProviderSettingKind::OpenConnectCaCert => "OpenConnect.CACert",
+            ProviderSettingKind::OpenConnectUsergroup => "OpenConnect.Usergroup",
+            ProviderSettingKind::VpncIpsecId => "VPNC.IPSec.ID",
+            ProviderSettingKind::VpncIpsecSecret => "VPNC.IPSec.Secret",
+            ProviderSettingKind::VpncXauthUsername => "VPNC.Xauth.Username",
+            ProviderSettingKind::VpncXauthPassword => "VPNC.Xauth.Password",
+            ProviderSettingKind::L2tpUser => "L2TP.User",
+            ProviderSettingKind::L2tpPassword => "L2TP.Password",
+            ProviderSettingKind::PptpUser => "PPTP.User",
+            ProviderSettingKind::PptpPassword => "PPTP.Password",
+            ProviderSettingKind::WireGuardAddress => "WireGuard.Address",
+            ProviderSettingKind::WireGuardPrivateKey => "WireGuard.PrivateKey",
+            ProviderSettingKind::WireGuardPublicKey => "WireGuard.PublicKey",
+            ProviderSettingKind::WireGuardAllowedIps => "WireGuard.AllowedIPs",
+            ProviderSettingKind::WireGuardPresharedKey => "WireGuard.PresharedKey",
+            ProviderSettingKind::WireGuardEndpointPort => "WireGuard.EndpointPort",
+            ProviderSettingKind::WireGuardPersistentKeepalive => "WireGuard.PersistentKeepalive",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejected_field(settings: &ProviderSettings) -> Option<String> {
+        match settings.validate() {
+            Err(Error::InvalidArgument(field)) => Some(field.into_owned()),
+            _ => None,
+        }
+    }
+
+    fn keys(settings: &ProviderSettings) -> Vec<&'static str> {
+        let mut keys: Vec<_> = settings.to_dict().keys().copied().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    fn wireguard() -> WireGuard {
+        WireGuard::new("10.8.0.2", "cHJpdmF0ZQ==", "cHVibGlj")
+    }
+
+    #[test]
+    fn validate_accepts_complete_settings() {
+        let settings = ProviderSettings::new("work", "vpn.example.com", "example.com", L2tp::new());
+        assert!(settings.validate().is_ok());
+
+        let settings = ProviderSettings::new(
+            "work",
+            "vpn.example.com",
+            "example.com",
+            wireguard().allowed_ip("0.0.0.0/0"),
+        );
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_empty_common_fields() {
+        let settings = ProviderSettings::new("", "vpn.example.com", "example.com", Pptp::new());
+        assert_eq!(rejected_field(&settings).as_deref(), Some("Name"));
+
+        let settings = ProviderSettings::new("work", "", "example.com", Pptp::new());
+        assert_eq!(rejected_field(&settings).as_deref(), Some("Host"));
+
+        let settings = ProviderSettings::new("work", "vpn.example.com", "", Pptp::new());
+        assert_eq!(rejected_field(&settings).as_deref(), Some("Domain"));
+    }
+
+    #[test]
+    fn validate_rejects_vpnc_without_group() {
+        let settings =
+            ProviderSettings::new("work", "vpn.example.com", "example.com", Vpnc::new(""));
+        assert_eq!(rejected_field(&settings).as_deref(), Some("VPNC.IPSec.ID"));
+    }
+
+    #[test]
+    fn validate_rejects_wireguard_without_allowed_ip() {
+        let settings = ProviderSettings::new("work", "vpn.example.com", "example.com", wireguard());
+        assert_eq!(
+            rejected_field(&settings).as_deref(),
+            Some("WireGuard.AllowedIPs")
+        );
+    }
+
+    #[test]
+    fn to_dict_common_keys() {
+        let settings = ProviderSettings::new("work", "vpn.example.com", "example.com", L2tp::new());
+        assert_eq!(keys(&settings), vec!["Domain", "Host", "Name", "Type"]);
+
+        let dict = settings
+            .network("10.0.0.0/255.255.0.0/10.0.0.1")
+            .network("10.1.0.0/255.255.0.0/10.1.0.1")
+            .to_dict();
+        assert_eq!(dict["Type"].0.as_str(), Some("l2tp"));
+        assert_eq!(
+            dict["Networks"].0.as_str(),
+            Some("10.0.0.0/255.255.0.0/10.0.0.1,10.1.0.0/255.255.0.0/10.1.0.1")
+        );
+    }
+
+    #[test]
+    fn to_dict_keys_per_vpn_type() {
+        let settings = |vpn: VpnSettings| ProviderSettings::new("n", "h", "d", vpn);
+        let common = ["Domain", "Host", "Name", "Type"];
+        let expect = |extra: &[&'static str]| {
+            let mut keys: Vec<_> = common.iter().chain(extra).copied().collect();
+            keys.sort_unstable();
+            keys
+        };
+
+        let openvpn = OpenVpn::new()
+            .ca_cert("ca.pem")
+            .cert("cert.pem")
+            .key("key.pem")
+            .proto("udp")
+            .port(1194)
+            .config_file("client.ovpn");
+        assert_eq!(
+            keys(&settings(openvpn.into())),
+            expect(&[
+                "OpenVPN.CACert",
+                "OpenVPN.Cert",
+                "OpenVPN.ConfigFile",
+                "OpenVPN.Key",
+                "OpenVPN.Port",
+                "OpenVPN.Proto",
+            ])
+        );
+
+        let openconnect = OpenConnect::new()
+            .protocol("anyconnect")
+            .server_cert("sha1:00")
+            .ca_cert("ca.pem")
+            .usergroup("staff");
+        assert_eq!(
+            keys(&settings(openconnect.into())),
+            expect(&[
+                "OpenConnect.CACert",
+                "OpenConnect.Protocol",
+                "OpenConnect.ServerCert",
+                "OpenConnect.Usergroup",
+            ])
+        );
+
+        assert_eq!(
+            keys(&settings(Vpnc::new("group").into())),
+            expect(&["VPNC.IPSec.ID"])
+        );
+        let vpnc = Vpnc::new("group")
+            .group_secret("secret")
+            .username("user")
+            .password("pass");
+        assert_eq!(
+            keys(&settings(vpnc.into())),
+            expect(&[
+                "VPNC.IPSec.ID",
+                "VPNC.IPSec.Secret",
+                "VPNC.Xauth.Password",
+                "VPNC.Xauth.Username",
+            ])
+        );
+
+        let l2tp = L2tp::new().user("user").password("pass");
+        assert_eq!(
+            keys(&settings(l2tp.into())),
+            expect(&["L2TP.Password", "L2TP.User"])
+        );
+
+        let pptp = Pptp::new().user("user").password("pass");
+        assert_eq!(
+            keys(&settings(pptp.into())),
+            expect(&["PPTP.Password", "PPTP.User"])
+        );
+
+        let wg = wireguard()
+            .allowed_ip("10.8.0.0/24")
+            .allowed_ip("192.168.0.0/16")
+            .preshared_key("cHNr")
+            .endpoint_port(51820)
+            .persistent_keepalive(25);
+        let dict = settings(wg.into()).to_dict();
+        assert_eq!(
+            dict["WireGuard.AllowedIPs"].0.as_str(),
+            Some("10.8.0.0/24,192.168.0.0/16")
+        );
+        assert_eq!(dict["WireGuard.EndpointPort"].0.as_str(), Some("51820"));
+        let mut wg_keys: Vec<_> = dict.keys().copied().collect();
+        wg_keys.sort_unstable();
+        assert_eq!(
+            wg_keys,
+            expect(&[
+                "WireGuard.Address",
+                "WireGuard.AllowedIPs",
+                "WireGuard.EndpointPort",
+                "WireGuard.PersistentKeepalive",
+                "WireGuard.PresharedKey",
+                "WireGuard.PrivateKey",
+                "WireGuard.PublicKey",
+            ])
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Retrying of D-Bus calls that fail transiently
+
+use super::{ConnmanError, Error};
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime};
+
+/// D-Bus errors returned when connman doesn't reply in time.
+const TIMEOUT_ERRORS: &[&str] = &[
+    "org.freedesktop.DBus.Error.NoReply",
+    "org.freedesktop.DBus.Error.Timeout",
+];
+
+/// How often, and how fast, to repeat calls that failed transiently.
+///
+/// Set on `Manager` with `with_retry_policy`; the `Service` and `Technology`
+/// handles it returns use the same policy. The default policy makes a single
+/// attempt.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    // `None` retries what `ConnmanError::is_retryable` accepts.
+    retry_on: Option<Vec<ConnmanError>>,
+    retry_timeouts: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new(1)
+    }
+}
+
+impl RetryPolicy {
+    /// Policy making up to `max_attempts` attempts in total.
+    ///
+    /// Retries start after 100ms and double up to 5s, each wait shortened by
+    /// up to half at random. The errors `ConnmanError::is_retryable` accepts
+    /// and D-Bus timeouts are retried.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.5,
+            retry_on: None,
+            retry_timeouts: true,
+        }
+    }
+
+    /// Wait before the first retry.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Upper bound for the wait between two attempts.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Factor the wait grows by after each retry.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Fraction of each wait, between 0 and 1, that is randomly left out.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Connman errors to retry on, replacing the default ones.
+    pub fn retry_on(mut self, errors: Vec<ConnmanError>) -> Self {
+        self.retry_on = Some(errors);
+        self
+    }
+
+    /// Whether to retry calls connman didn't reply to in time.
+    pub fn retry_timeouts(mut self, retry: bool) -> Self {
+        self.retry_timeouts = retry;
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Whether `err` is worth another attempt under this policy.
+    pub fn should_retry(&self, err: &Error) -> bool {
+        self.should_retry_with(err, self.retry_timeouts)
+    }
+
+    fn should_retry_with(&self, err: &Error, retry_timeouts: bool) -> bool {
+        match err {
+            Error::Connman { kind, .. } => match &self.retry_on {
+                Some(errors) => errors.contains(kind),
+                None => kind.is_retryable(),
+            },
+            Error::DbusError(e) => {
+                retry_timeouts && matches!(e.name(), Some(n) if TIMEOUT_ERRORS.contains(&n))
+            }
+            _ => false,
+        }
+    }
+
+    /// Wait before retry number `retry`, starting at 0.
+    fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(retry as i32);
+        let backoff = backoff.min(self.max_backoff.as_secs_f64());
+        Duration::from_secs_f64(backoff * (1.0 - self.jitter * random_fraction()))
+    }
+
+    /// Runs the call made by `call` until it succeeds, fails with an error
+    /// that isn't retried, or runs out of attempts.
+    pub(crate) async fn run<F, Fut, T, E>(&self, call: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Into<Error>,
+    {
+        self.run_with(call, self.retry_timeouts).await
+    }
+
+    /// Like `run`, but for calls that create an object on the connman side.
+    ///
+    /// A call connman didn't reply to in time may still have gone through, so
+    /// repeating it could create a duplicate. Only the connman errors of the
+    /// policy are retried, never timeouts.
+    pub(crate) async fn run_non_idempotent<F, Fut, T, E>(&self, call: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Into<Error>,
+    {
+        self.run_with(call, false).await
+    }
+
+    async fn run_with<F, Fut, T, E>(&self, mut call: F, retry_timeouts: bool) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Into<Error>,
+    {
+        let mut retry = 0;
+        loop {
+            match call().await.map_err(Into::into) {
+                Err(e)
+                    if retry + 1 < self.max_attempts
+                        && self.should_retry_with(&e, retry_timeouts) =>
+                {
+                    tokio::time::delay_for(self.backoff(retry)).await;
+                    retry += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// Random number in `[0, 1)`, from the randomly seeded std hasher.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(elapsed) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(elapsed.as_nanos());
+    }
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn error(name: &str) -> dbus::Error {
+        dbus::Error::new_custom(name, "test")
+    }
+
+    /// Number of calls `run` (or `run_non_idempotent`) makes when every call
+    /// fails with `name`.
+    fn attempts(policy: &RetryPolicy, name: &str, idempotent: bool) -> u32 {
+        let calls = Cell::new(0);
+        let call = || {
+            calls.set(calls.get() + 1);
+            async { Err::<(), _>(error(name)) }
+        };
+        let mut rt = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_time()
+            .build()
+            .unwrap();
+        let res = if idempotent {
+            rt.block_on(policy.run(call))
+        } else {
+            rt.block_on(policy.run_non_idempotent(call))
+        };
+        assert!(res.is_err());
+        calls.get()
+    }
+
+    #[test]
+    fn backoff_grows_up_to_cap() {
+        let policy = RetryPolicy::new(10)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(350))
+            .jitter(0.0);
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(350));
+        assert_eq!(policy.backoff(20), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn backoff_jitter_shortens() {
+        let policy = RetryPolicy::new(10).jitter(0.5);
+        for _ in 0..100 {
+            let backoff = policy.backoff(0);
+            assert!(backoff > Duration::from_millis(50));
+            assert!(backoff <= Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn should_retry() {
+        let policy = RetryPolicy::new(3);
+        assert!(policy.should_retry(&error("net.connman.Error.InProgress").into()));
+        assert!(policy.should_retry(&error("org.freedesktop.DBus.Error.NoReply").into()));
+        assert!(!policy.should_retry(&error("net.connman.Error.PermissionDenied").into()));
+
+        let policy = policy.retry_timeouts(false);
+        assert!(!policy.should_retry(&error("org.freedesktop.DBus.Error.NoReply").into()));
+
+        let policy = policy.retry_on(vec![ConnmanError::PermissionDenied]);
+        assert!(policy.should_retry(&error("net.connman.Error.PermissionDenied").into()));
+        assert!(!policy.should_retry(&error("net.connman.Error.InProgress").into()));
+    }
+
+    #[test]
+    fn attempt_count() {
+        let policy = RetryPolicy::new(3).initial_backoff(Duration::from_millis(0));
+        assert_eq!(attempts(&policy, "net.connman.Error.InProgress", true), 3);
+        assert_eq!(
+            attempts(&policy, "net.connman.Error.PermissionDenied", true),
+            1
+        );
+        assert_eq!(
+            attempts(
+                &RetryPolicy::default(),
+                "net.connman.Error.InProgress",
+                true
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn non_idempotent_skips_timeouts() {
+        let policy = RetryPolicy::new(3).initial_backoff(Duration::from_millis(0));
+        assert_eq!(
+            attempts(&policy, "org.freedesktop.DBus.Error.NoReply", true),
+            3
+        );
+        assert_eq!(
+            attempts(&policy, "org.freedesktop.DBus.Error.NoReply", false),
+            1
+        );
+        assert_eq!(attempts(&policy, "net.connman.Error.InProgress", false), 3);
+    }
+}
@@ -1,25 +1,39 @@
 use dbus::arg;
+use dbus::message::{MatchRule, MessageType, SignalArgs};
 use dbus::nonblock::{NonblockReply, Proxy as DBusProxy, SyncConnection};
+use futures::future::{select, Either};
+use futures::stream::StreamExt;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
 
 #[cfg(feature = "introspection")]
 use xml::reader::EventReader;
 
-use super::gen::service::Service as IService;
-use super::Error as ApiError;
-use super::{FromProperties, PropertyError};
+use super::gen::service::{Service as IService, ServicePropertyChanged};
+use super::retry::RetryPolicy;
+use super::service_id::ServiceId;
+use super::signal::SignalStream;
+use super::{
+    insert_property, insert_property_opt, FromProperties, IntoProperties, OwnedValue, ParseMode,
+    PropertyError, PropertyParser,
+};
+use super::{ConnmanError, Error as ApiError};
 use crate::api::{get_property_argiter, RefArgIter, RefArgMap};
-use dbus::arg::{cast, RefArg, Variant};
+use dbus::arg::{RefArg, Variant};
 use std::convert::TryFrom;
 use std::ops::Deref;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Futures-aware wrapper struct for connman Service object.
+#[derive(Clone)]
 pub struct Service<C> {
     proxy: DBusProxy<'static, C>,
     pub props: Properties,
+    parse_mode: ParseMode,
+    retry: RetryPolicy,
 }
 
 impl<C> Service<C> {
@@ -29,14 +43,51 @@ impl<C> Service<C> {
         args: RefArgMap,
         timeout: Duration,
     ) -> Result<Self, ApiError> {
-        let properties = Properties::try_from(args).map_err(ApiError::from)?;
+        Self::new_with_mode(connection, path, args, timeout, ParseMode::Strict)
+    }
+
+    /// Like `new`, parsing the properties (now and on refresh) with `mode`.
+    pub fn new_with_mode(
+        connection: C,
+        path: dbus::Path<'static>,
+        args: RefArgMap,
+        timeout: Duration,
+        mode: ParseMode,
+    ) -> Result<Self, ApiError> {
+        Ok(Self::try_new(
+            connection,
+            path,
+            args,
+            timeout,
+            mode,
+            RetryPolicy::default(),
+        )?)
+    }
+
+    pub(crate) fn try_new(
+        connection: C,
+        path: dbus::Path<'static>,
+        args: RefArgMap,
+        timeout: Duration,
+        mode: ParseMode,
+        retry: RetryPolicy,
+    ) -> Result<Self, PropertyError> {
+        let properties = Properties::parse(args, mode)?;
 
         Ok(Service {
             proxy: Self::proxy(path, timeout, connection),
             props: properties,
+            parse_mode: mode,
+            retry,
         })
     }
 
+    /// Set the policy for retrying failed calls.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
     pub fn proxy(path: dbus::Path<'static>, timeout: Duration, conn: C) -> DBusProxy<'static, C> {
         let proxy = DBusProxy::new("net.connman", path, timeout, conn);
         proxy
@@ -45,6 +96,11 @@ impl<C> Service<C> {
     pub fn path(&self) -> &dbus::Path<'static> {
         &self.proxy.path
     }
+
+    /// Identifier parsed from the service's object path.
+    pub fn id(&self) -> Result<ServiceId, PropertyError> {
+        ServiceId::from_path(self.path())
+    }
 }
 
 impl<T: NonblockReply, C: Deref<Target = T>> Service<C> {
@@ -52,33 +108,271 @@ impl<T: NonblockReply, C: Deref<Target = T>> Service<C> {
     pub async fn introspect(&self) -> Result<EventReader<std::io::Cursor<Vec<u8>>>, ApiError> {
         use crate::api::gen::service::OrgFreedesktopDBusIntrospectable as Introspectable;
 
-        let s = Introspectable::introspect(&self.proxy).await?;
+        let s = self
+            .retry
+            .run(|| Introspectable::introspect(&self.proxy))
+            .await?;
         let rdr = std::io::Cursor::new(s.into_bytes());
         Ok(EventReader::new(rdr))
     }
 
     pub async fn connect(&self) -> Result<(), ApiError> {
-        Ok(IService::connect(&self.proxy).await?)
+        self.retry.run(|| IService::connect(&self.proxy)).await
     }
 
     pub async fn disconnect(&self) -> Result<(), ApiError> {
-        Ok(IService::disconnect(&self.proxy).await?)
+        self.retry.run(|| IService::disconnect(&self.proxy)).await
     }
 
     pub async fn remove(&self) -> Result<(), ApiError> {
-        Ok(IService::remove(&self.proxy).await?)
+        self.retry.run(|| IService::remove(&self.proxy)).await
     }
 
     pub async fn move_before(&self, service: &Service<C>) -> Result<(), ApiError> {
-        Ok(IService::move_before(&self.proxy, service.path().clone()).await?)
+        self.retry
+            .run(|| IService::move_before(&self.proxy, service.path().clone()))
+            .await
     }
 
     pub async fn move_after(&self, service: &Service<C>) -> Result<(), ApiError> {
-        Ok(IService::move_after(&self.proxy, service.path().clone()).await?)
+        self.retry
+            .run(|| IService::move_after(&self.proxy, service.path().clone()))
+            .await
     }
 }
 
+impl Service<Arc<SyncConnection>> {
+    /// Subscribe to property changes of this service.
+    ///
+    /// The underlying match rule is removed once the returned stream is dropped.
+    /// See `SignalStream` on holding several overlapping streams at once.
+    pub async fn events(&self) -> Result<SignalStream<PropertyChanged>, ApiError> {
+        let rule = MatchRule::new()
+            .with_type(MessageType::Signal)
+            .with_sender("net.connman")
+            .with_path(self.proxy.path.clone())
+            .with_interface(ServicePropertyChanged::INTERFACE)
+            .with_member(ServicePropertyChanged::NAME);
+
+        SignalStream::new(self.proxy.connection.clone(), rule, &self.retry, |msg| {
+            ServicePropertyChanged::from_message(&msg).map(|s| PropertyChanged {
+                name: s.name,
+                value: s.value,
+            })
+        })
+        .await
+    }
+
+    /// Connect, then wait until the service reaches `target` or fails.
+    ///
+    /// `State::Online` satisfies a `State::Ready` target. A failed service
+    /// resolves to `Error::ServiceFailure` with the service `Error` property,
+    /// and `Error::Timeout` is returned if neither happens within `timeout`.
+    /// If the signal stream ends first, `Error::SignalStreamClosed` is
+    /// returned.
+    /// While `events()` is held, signal match mode must be on; see
+    /// `SignalStream`.
+    pub async fn connect_and_wait(
+        &self,
+        target: State,
+        timeout: Duration,
+    ) -> Result<State, ApiError> {
+        let path = self.path().clone();
+        tokio::time::timeout(timeout, self.wait_for_state(target))
+            .await
+            .map_err(|_| ApiError::Timeout(Cow::Owned(path.to_string())))?
+    }
+
+    async fn wait_for_state(&self, target: State) -> Result<State, ApiError> {
+        let reached =
+            |state: &State| *state == target || (target == State::Ready && *state == State::Online);
+
+        // Subscribe first so no change between the call and the reply is missed.
+        let mut events = self.events().await?;
+        let mut connect = Some(Box::pin(self.retry.run(|| IService::connect(&self.proxy))));
+        let mut error = None;
+
+        loop {
+            let event = match connect.take() {
+                Some(call) => match select(call, events.next()).await {
+                    Either::Left((res, _)) => {
+                        if let Err(e) = res {
+                            if e.connman_error() != Some(&ConnmanError::AlreadyConnected) {
+                                return Err(e);
+                            }
+                        }
+                        // The state may have settled before any signal was seen.
+                        let props = Properties::parse(
+                            self.retry
+                                .run(|| IService::get_properties(&self.proxy))
+                                .await?,
+                            self.parse_mode,
+                        )?;
+                        if reached(&props.state) {
+                            return Ok(props.state);
+                        }
+                        if props.state == State::Failure {
+                            return Err(ApiError::ServiceFailure(props.error));
+                        }
+                        continue;
+                    }
+                    Either::Right((event, call)) => {
+                        connect = Some(call);
+                        event
+                    }
+                },
+                None => events.next().await,
+            };
+
+            let event = match event {
+                Some(event) => event,
+                None => {
+                    return Err(ApiError::SignalStreamClosed(Cow::Owned(
+                        self.path().to_string(),
+                    )))
+                }
+            };
+            let mut props = RefArgMap::new();
+            props.insert(event.name.clone(), Variant(event.value.0.box_clone()));
+
+            match PropertyKind::from_str(&event.name) {
+                Ok(PropertyKind::Error) => {
+                    error = Error::from_properties(&props, PropertyKind::Error.into()).ok();
+                }
+                Ok(PropertyKind::State) => {
+                    let state = State::from_properties(&props, PropertyKind::State.into())?;
+                    if reached(&state) {
+                        return Ok(state);
+                    }
+                    if state == State::Failure {
+                        if error.is_none() {
+                            let a = self
+                                .retry
+                                .run(|| IService::get_properties(&self.proxy))
+                                .await?;
+                            error =
+                                FromProperties::from_properties(&a, PropertyKind::Error.into())?;
+                        }
+                        return Err(ApiError::ServiceFailure(error));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A single property change of a service.
 #[derive(Debug)]
+pub struct PropertyChanged {
+    pub name: String,
+    pub value: Variant<Box<dyn RefArg + 'static>>,
+}
+
+impl<T: NonblockReply, C: Deref<Target = T>> Service<C> {
+    pub async fn set_autoconnect(&self, autoconnect: bool) -> Result<(), ApiError> {
+        self.retry
+            .run(|| {
+                IService::set_property(&self.proxy, PropertyKind::AutoConnect.into(), autoconnect)
+            })
+            .await
+    }
+
+    /// Set the manually-configured nameservers; an empty list reverts to the
+    /// ones obtained automatically.
+    pub async fn set_nameservers_config(&self, nameservers: Vec<IpAddr>) -> Result<(), ApiError> {
+        self.retry
+            .run(|| {
+                IService::set_property(
+                    &self.proxy,
+                    PropertyKind::NameserversConfiguration.into(),
+                    nameservers
+                        .iter()
+                        .map(IpAddr::to_string)
+                        .collect::<Vec<String>>(),
+                )
+            })
+            .await
+    }
+
+    /// Set the manually-configured timeservers; an empty list reverts to the
+    /// ones obtained automatically.
+    pub async fn set_timeservers_config(&self, timeservers: Vec<String>) -> Result<(), ApiError> {
+        self.retry
+            .run(|| {
+                IService::set_property(
+                    &self.proxy,
+                    PropertyKind::TimeserversConfiguration.into(),
+                    timeservers.clone(),
+                )
+            })
+            .await
+    }
+
+    /// Set the manually-configured search domains; an empty list reverts to
+    /// the ones obtained automatically.
+    pub async fn set_domains_config(&self, domains: Vec<String>) -> Result<(), ApiError> {
+        self.retry
+            .run(|| {
+                IService::set_property(
+                    &self.proxy,
+                    PropertyKind::DomainsConfiguration.into(),
+                    domains.clone(),
+                )
+            })
+            .await
+    }
+
+    /// Set the Ipv4 configuration, e.g. a static address with
+    /// `Ipv4Method::Manual`; unset fields are left out.
+    pub async fn set_ipv4_config(&self, ipv4: &Ipv4) -> Result<(), ApiError> {
+        self.retry
+            .run(|| {
+                IService::set_property(
+                    &self.proxy,
+                    PropertyKind::Ipv4Configuration.into(),
+                    ipv4.clone().into_properties(),
+                )
+            })
+            .await
+    }
+
+    /// Set the Ipv6 configuration; unset fields are left out.
+    pub async fn set_ipv6_config(&self, ipv6: &Ipv6) -> Result<(), ApiError> {
+        self.retry
+            .run(|| {
+                IService::set_property(
+                    &self.proxy,
+                    PropertyKind::Ipv6Configuration.into(),
+                    ipv6.clone().into_properties(),
+                )
+            })
+            .await
+    }
+
+    /// Set the proxy configuration; unset fields are left out.
+    pub async fn set_proxy_config(&self, proxy: &Proxy) -> Result<(), ApiError> {
+        self.retry
+            .run(|| {
+                IService::set_property(
+                    &self.proxy,
+                    PropertyKind::ProxyConfiguration.into(),
+                    proxy.clone().into_properties(),
+                )
+            })
+            .await
+    }
+
+    pub async fn set_mdns_config(&self, mdns: bool) -> Result<(), ApiError> {
+        self.retry
+            .run(|| {
+                IService::set_property(&self.proxy, PropertyKind::MdnsConfiguration.into(), mdns)
+            })
+            .await
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Properties {
     /// Connection state
     pub state: State,
@@ -89,8 +383,7 @@ pub struct Properties {
     /// Service name
     pub type_: Option<Type>,
     /// Service name
-    // TODO: enum variants?
-    pub security: Option<Vec<String>>,
+    pub security: Option<Vec<Security>>,
     /// Signal strength
     pub strength: Option<u8>,
     /// Set if favorite or User-selected
@@ -102,9 +395,9 @@ pub struct Properties {
     /// Set if service is roaming
     pub roaming: Option<bool>,
     /// List of currently-active nameservers
-    pub nameservers: Vec<String>, // TODO: Deserialize `String` into `IpAddr`?
+    pub nameservers: Vec<IpAddr>,
     /// List of manually-configured nameservers
-    pub nameservers_config: Vec<String>, // TODO: Deserialize `String` into `IpAddr`?
+    pub nameservers_config: Vec<IpAddr>,
     /// List of currently-active timeservers
     pub timeservers: Vec<String>,
     /// List of manually-configured timeservers
@@ -133,6 +426,13 @@ pub struct Properties {
     pub mdns: Option<bool>,
     /// Whether or not mDNS (config) support is enabled
     pub mdns_config: Option<bool>,
+    /// Properties not known to this crate, e.g. ones added by newer connman
+    /// releases
+    pub extra: HashMap<String, OwnedValue>,
+    /// Problems found while parsing; see `ParseMode`
+    ///
+    /// These are not serialized by `into_properties`.
+    pub warnings: Vec<PropertyError>,
 }
 
 impl FromProperties for State {
@@ -179,9 +479,18 @@ impl FromProperties for Ipv4 {
         } else {
             None
         };
-        let address = m.get(Ipv4Kind::Address.into()).cloned();
-        let netmask = m.get(Ipv4Kind::Netmask.into()).cloned();
-        let gateway = m.get(Ipv4Kind::Gateway.into()).cloned();
+        let addr = |kind: Ipv4Kind| -> Result<Option<Ipv4Addr>, PropertyError> {
+            let key: &'static str = kind.into();
+            m.get(key)
+                .map(|addr| {
+                    addr.parse()
+                        .map_err(|_| cast_subfield(prop_name, key, addr))
+                })
+                .transpose()
+        };
+        let address = addr(Ipv4Kind::Address)?;
+        let netmask = addr(Ipv4Kind::Netmask)?;
+        let gateway = addr(Ipv4Kind::Gateway)?;
 
         Ok(Ipv4 {
             method,
@@ -199,8 +508,17 @@ impl FromProperties for Ipv6 {
     ) -> Result<Self, PropertyError> {
         let mut i = get_property_argiter(properties, prop_name)?;
         let mut m: HashMap<&str, &str> = HashMap::new();
+        let mut prefix_length = None;
         while let Some(key) = i.next().and_then(|k| k.as_str()) {
-            if let Some(val) = i.next().and_then(|v| v.as_str()) {
+            let val = i.next();
+            if key == <&str>::from(Ipv6Kind::PrefixLength) {
+                // Sent as a byte by connman
+                prefix_length = val.and_then(|v| {
+                    v.as_u64()
+                        .map(|len| len as u8)
+                        .or_else(|| v.as_str().and_then(|len| len.parse::<u8>().ok()))
+                });
+            } else if let Some(val) = val.and_then(|v| v.as_str()) {
                 let _ = m.insert(key, val);
             }
         }
@@ -209,12 +527,17 @@ impl FromProperties for Ipv6 {
             .get(Ipv6Kind::Method.into())
             .and_then(|method| method.parse::<Ipv6Method>().ok());
 
-        let address = m.get(Ipv6Kind::Address.into()).copied().map(String::from);
-        let prefix_length = m
-            .get(Ipv6Kind::PrefixLength.into())
-            .and_then(|val| val.parse::<u8>().ok());
-
-        let gateway = m.get(Ipv6Kind::Gateway.into()).copied().map(String::from);
+        let addr = |kind: Ipv6Kind| -> Result<Option<Ipv6Addr>, PropertyError> {
+            let key: &'static str = kind.into();
+            m.get(key)
+                .map(|addr| {
+                    addr.parse()
+                        .map_err(|_| cast_subfield(prop_name, key, addr))
+                })
+                .transpose()
+        };
+        let address = addr(Ipv6Kind::Address)?;
+        let gateway = addr(Ipv6Kind::Gateway)?;
         let privacy = m
             .get(Ipv6Kind::Privacy.into())
             .and_then(|privacy| privacy.parse::<Ipv6Privacy>().ok());
@@ -254,11 +577,11 @@ impl FromProperties for Proxy {
 
         let servers = m
             .get(ProxyKind::Servers.into())
-            .and_then(|refarg| cast::<Vec<String>>(&refarg.box_clone()).cloned());
+            .and_then(|refarg| super::as_strings(*refarg));
 
         let excludes = m
             .get(ProxyKind::Excludes.into())
-            .and_then(|refarg| cast::<Vec<String>>(&refarg.box_clone()).cloned());
+            .and_then(|refarg| super::as_strings(*refarg));
 
         Ok(Proxy {
             method,
@@ -312,7 +635,13 @@ impl FromProperties for Ethernet {
             mtu: None,
         };
         while let Some(key) = i.next().and_then(|k| k.as_str()) {
-            let kind = EthernetKind::from_str(key).expect("Unhandled EthernetKind variant");
+            let kind = match EthernetKind::from_str(key) {
+                Ok(kind) => kind,
+                Err(_) => {
+                    let _ = i.next();
+                    continue;
+                }
+            };
             match kind {
                 EthernetKind::Method => {
                     if let Some(method) = i
@@ -344,64 +673,358 @@ impl FromProperties for Ethernet {
     }
 }
 
-impl Properties {
-    pub fn try_from(props: RefArgMap) -> Result<Self, PropertyError> {
-        let state = State::from_properties(&props, PropertyKind::State.into())?;
+/// Cast error naming the dict entry `key` of property `prop_name`.
+fn cast_subfield(prop_name: &str, key: &str, value: &str) -> PropertyError {
+    PropertyError::Cast(Cow::Owned(format!("{}.{}: '{}'", prop_name, key, value)))
+}
 
-        let error: Option<Error> =
-            FromProperties::from_properties(&props, PropertyKind::Error.into())?;
+impl IntoProperties for Ipv4 {
+    fn into_properties(self) -> RefArgMap {
+        let mut props = RefArgMap::new();
+        insert_property_opt(
+            &mut props,
+            Ipv4Kind::Method.into(),
+            self.method
+                .map(|m| Cow::<'static, str>::from(m).into_owned()),
+        );
+        insert_property_opt(
+            &mut props,
+            Ipv4Kind::Address.into(),
+            self.address.map(|addr| addr.to_string()),
+        );
+        insert_property_opt(
+            &mut props,
+            Ipv4Kind::Netmask.into(),
+            self.netmask.map(|addr| addr.to_string()),
+        );
+        insert_property_opt(
+            &mut props,
+            Ipv4Kind::Gateway.into(),
+            self.gateway.map(|addr| addr.to_string()),
+        );
+        props
+    }
+}
 
-        let name: Option<String> =
-            FromProperties::from_properties(&props, PropertyKind::Name.into())?;
+impl IntoProperties for Ipv6 {
+    fn into_properties(self) -> RefArgMap {
+        let mut props = RefArgMap::new();
+        insert_property_opt(
+            &mut props,
+            Ipv6Kind::Method.into(),
+            self.method
+                .map(|m| Cow::<'static, str>::from(m).into_owned()),
+        );
+        insert_property_opt(
+            &mut props,
+            Ipv6Kind::Address.into(),
+            self.address.map(|addr| addr.to_string()),
+        );
+        insert_property_opt(
+            &mut props,
+            Ipv6Kind::PrefixLength.into(),
+            self.prefix_length,
+        );
+        insert_property_opt(
+            &mut props,
+            Ipv6Kind::Gateway.into(),
+            self.gateway.map(|addr| addr.to_string()),
+        );
+        insert_property_opt(
+            &mut props,
+            Ipv6Kind::Privacy.into(),
+            self.privacy
+                .map(|p| Cow::<'static, str>::from(p).into_owned()),
+        );
+        props
+    }
+}
 
-        let type_: Option<Type> =
-            FromProperties::from_properties(&props, PropertyKind::Type.into())?;
+impl IntoProperties for Proxy {
+    fn into_properties(self) -> RefArgMap {
+        let mut props = RefArgMap::new();
+        insert_property_opt(
+            &mut props,
+            ProxyKind::Method.into(),
+            self.method
+                .map(|m| Cow::<'static, str>::from(m).into_owned()),
+        );
+        insert_property_opt(&mut props, ProxyKind::Url.into(), self.url);
+        insert_property_opt(&mut props, ProxyKind::Servers.into(), self.servers);
+        insert_property_opt(&mut props, ProxyKind::Excludes.into(), self.excludes);
+        props
+    }
+}
 
-        let security: Option<Vec<String>> =
-            FromProperties::from_properties(&props, PropertyKind::Security.into())?;
+impl IntoProperties for Provider {
+    fn into_properties(self) -> RefArgMap {
+        let mut props = RefArgMap::new();
+        insert_property_opt(&mut props, ProviderKind::Host.into(), self.host);
+        insert_property_opt(&mut props, ProviderKind::Domain.into(), self.domain);
+        insert_property_opt(&mut props, ProviderKind::Name.into(), self.name);
+        insert_property_opt(&mut props, ProviderKind::Type.into(), self.type_);
+        props
+    }
+}
 
-        let strength: Option<u8> =
-            FromProperties::from_properties(&props, PropertyKind::Strength.into())?;
+impl IntoProperties for Ethernet {
+    fn into_properties(self) -> RefArgMap {
+        let mut props = RefArgMap::new();
+        insert_property_opt(
+            &mut props,
+            EthernetKind::Method.into(),
+            self.method
+                .map(|m| Cow::<'static, str>::from(m).into_owned()),
+        );
+        insert_property_opt(&mut props, EthernetKind::Interface.into(), self.interface);
+        insert_property_opt(&mut props, EthernetKind::Address.into(), self.address);
+        insert_property_opt(&mut props, EthernetKind::Mtu.into(), self.mtu);
+        props
+    }
+}
 
-        let favorite = bool::from_properties(&props, PropertyKind::Favorite.into())?;
-        let immutable = bool::from_properties(&props, PropertyKind::Immutable.into())?;
-        let autoconnect = bool::from_properties(&props, PropertyKind::AutoConnect.into())?;
+impl IntoProperties for Properties {
+    fn into_properties(self) -> RefArgMap {
+        let mut props: RefArgMap = self
+            .extra
+            .into_iter()
+            .map(|(key, value)| (key, value.0))
+            .collect();
+
+        insert_property(
+            &mut props,
+            PropertyKind::State.into(),
+            Cow::<'static, str>::from(self.state).into_owned(),
+        );
+        insert_property_opt(
+            &mut props,
+            PropertyKind::Error.into(),
+            self.error
+                .map(|e| Cow::<'static, str>::from(e).into_owned()),
+        );
+        insert_property_opt(&mut props, PropertyKind::Name.into(), self.name);
+        insert_property_opt(
+            &mut props,
+            PropertyKind::Type.into(),
+            self.type_
+                .map(|t| Cow::<'static, str>::from(t).into_owned()),
+        );
+        insert_property_opt(
+            &mut props,
+            PropertyKind::Security.into(),
+            self.security.map(|security| {
+                security
+                    .into_iter()
+                    .map(|s| Cow::from(s).into_owned())
+                    .collect::<Vec<String>>()
+            }),
+        );
+        insert_property_opt(&mut props, PropertyKind::Strength.into(), self.strength);
+        insert_property(&mut props, PropertyKind::Favorite.into(), self.favorite);
+        insert_property(&mut props, PropertyKind::Immutable.into(), self.immutable);
+        insert_property(
+            &mut props,
+            PropertyKind::AutoConnect.into(),
+            self.autoconnect,
+        );
+        insert_property_opt(&mut props, PropertyKind::Roaming.into(), self.roaming);
+        insert_property(
+            &mut props,
+            PropertyKind::Nameservers.into(),
+            self.nameservers
+                .iter()
+                .map(IpAddr::to_string)
+                .collect::<Vec<String>>(),
+        );
+        insert_property(
+            &mut props,
+            PropertyKind::NameserversConfiguration.into(),
+            self.nameservers_config
+                .iter()
+                .map(IpAddr::to_string)
+                .collect::<Vec<String>>(),
+        );
+        insert_property(
+            &mut props,
+            PropertyKind::Timeservers.into(),
+            self.timeservers,
+        );
+        insert_property(
+            &mut props,
+            PropertyKind::TimeserversConfiguration.into(),
+            self.timeservers_config,
+        );
+        insert_property(&mut props, PropertyKind::Domains.into(), self.domains);
+        insert_property(
+            &mut props,
+            PropertyKind::DomainsConfiguration.into(),
+            self.domains_config,
+        );
+        insert_property(
+            &mut props,
+            PropertyKind::Ipv4.into(),
+            self.ipv4.into_properties(),
+        );
+        insert_property(
+            &mut props,
+            PropertyKind::Ipv4Configuration.into(),
+            self.ipv4_config.into_properties(),
+        );
+        insert_property(
+            &mut props,
+            PropertyKind::Ipv6.into(),
+            self.ipv6.into_properties(),
+        );
+        insert_property(
+            &mut props,
+            PropertyKind::Ipv6Configuration.into(),
+            self.ipv6_config.into_properties(),
+        );
+        insert_property(
+            &mut props,
+            PropertyKind::Proxy.into(),
+            self.proxy.into_properties(),
+        );
+        insert_property(
+            &mut props,
+            PropertyKind::ProxyConfiguration.into(),
+            self.proxy_config.into_properties(),
+        );
+        insert_property(
+            &mut props,
+            PropertyKind::Provider.into(),
+            self.provider.into_properties(),
+        );
+        insert_property(
+            &mut props,
+            PropertyKind::Ethernet.into(),
+            self.ethernet.into_properties(),
+        );
+        insert_property_opt(&mut props, PropertyKind::Mdns.into(), self.mdns);
+        insert_property_opt(
+            &mut props,
+            PropertyKind::MdnsConfiguration.into(),
+            self.mdns_config,
+        );
+        props
+    }
+}
 
-        let roaming: Option<bool> =
-            FromProperties::from_properties(&props, PropertyKind::Roaming.into())?;
+impl Properties {
+    pub fn try_from(props: RefArgMap) -> Result<Self, PropertyError> {
+        Self::parse(props, ParseMode::Strict)
+    }
 
-        let nameservers: Vec<String> =
-            FromProperties::from_properties(&props, PropertyKind::Nameservers.into())?;
-        let nameservers_config: Vec<String> =
-            FromProperties::from_properties(&props, PropertyKind::NameserversConfiguration.into())?;
-        let timeservers: Vec<String> =
-            FromProperties::from_properties(&props, PropertyKind::Timeservers.into())?;
+    pub fn parse(props: RefArgMap, mode: ParseMode) -> Result<Self, PropertyError> {
+        let mut p = PropertyParser::new(&props, mode);
+
+        let state = p.get_with(
+            PropertyKind::State.into(),
+            |props, name| State::from_properties(props, name).map(Some),
+            || None,
+        )?;
+        let error: Option<Error> = p.get(PropertyKind::Error.into())?;
+        let name: Option<String> = p.get(PropertyKind::Name.into())?;
+        let type_: Option<Type> = p.get(PropertyKind::Type.into())?;
+        let security: Option<Vec<Security>> =
+            p.get_with(PropertyKind::Security.into(), parse_security, || None)?;
+        let strength: Option<u8> = p.get(PropertyKind::Strength.into())?;
+
+        let favorite: bool = p.get(PropertyKind::Favorite.into())?;
+        let immutable: bool = p.get(PropertyKind::Immutable.into())?;
+        let autoconnect: bool = p.get(PropertyKind::AutoConnect.into())?;
+        let roaming: Option<bool> = p.get(PropertyKind::Roaming.into())?;
+
+        let nameservers: Vec<IpAddr> = p.get_with(
+            PropertyKind::Nameservers.into(),
+            super::get_property_vec_fromstr,
+            Vec::new,
+        )?;
+        let nameservers_config: Vec<IpAddr> = p.get_with(
+            PropertyKind::NameserversConfiguration.into(),
+            super::get_property_vec_fromstr,
+            Vec::new,
+        )?;
+        let timeservers: Vec<String> = p.get(PropertyKind::Timeservers.into())?;
         let timeservers_config: Vec<String> =
-            FromProperties::from_properties(&props, PropertyKind::TimeserversConfiguration.into())?;
-        let domains: Vec<String> =
-            FromProperties::from_properties(&props, PropertyKind::Domains.into())?;
-        let domains_config: Vec<String> =
-            FromProperties::from_properties(&props, PropertyKind::DomainsConfiguration.into())?;
+            p.get(PropertyKind::TimeserversConfiguration.into())?;
+        let domains: Vec<String> = p.get(PropertyKind::Domains.into())?;
+        let domains_config: Vec<String> = p.get(PropertyKind::DomainsConfiguration.into())?;
 
-        let ipv4 = Ipv4::from_properties(&props, PropertyKind::Ipv4.into())?;
-        let ipv4_config = Ipv4::from_properties(&props, PropertyKind::Ipv4Configuration.into())?;
-        let ipv6 = Ipv6::from_properties(&props, PropertyKind::Ipv6.into())?;
-        let ipv6_config = Ipv6::from_properties(&props, PropertyKind::Ipv6Configuration.into())?;
+        let ipv4: Ipv4 = p.get(PropertyKind::Ipv4.into())?;
+        let ipv4_config: Ipv4 = p.get(PropertyKind::Ipv4Configuration.into())?;
+        let ipv6: Ipv6 = p.get(PropertyKind::Ipv6.into())?;
+        let ipv6_config: Ipv6 = p.get(PropertyKind::Ipv6Configuration.into())?;
 
-        let proxy = Proxy::from_properties(&props, PropertyKind::Proxy.into())?;
-        let proxy_config = Proxy::from_properties(&props, PropertyKind::ProxyConfiguration.into())?;
+        let proxy: Proxy = p.get(PropertyKind::Proxy.into())?;
+        let proxy_config: Proxy = p.get(PropertyKind::ProxyConfiguration.into())?;
 
-        let provider = Provider::from_properties(&props, PropertyKind::Provider.into())?;
+        let provider: Provider = p.get(PropertyKind::Provider.into())?;
 
-        let ethernet = Ethernet::from_properties(&props, PropertyKind::Ethernet.into())?;
+        let ethernet: Ethernet = p.get(PropertyKind::Ethernet.into())?;
 
-        let mdns: Option<bool> =
-            FromProperties::from_properties(&props, PropertyKind::Mdns.into())?;
-        let mdns_config: Option<bool> =
-            FromProperties::from_properties(&props, PropertyKind::MdnsConfiguration.into())?;
+        let mdns: Option<bool> = p.get(PropertyKind::Mdns.into())?;
+        let mdns_config: Option<bool> = p.get(PropertyKind::MdnsConfiguration.into())?;
+
+        // Type and Security are open-ended, so new values only warn.
+        if let Some(Type::Unknown(v)) = &type_ {
+            p.unknown_value(PropertyKind::Type.into(), v);
+        }
+        for security in security.iter().flatten() {
+            if let Security::Unknown(v) = security {
+                p.unknown_value(PropertyKind::Security.into(), v);
+            }
+        }
+
+        let mut unknown =
+            |kind: PropertyKind, value: &str| p.unrecognized_value(kind.into(), value);
+        if let Some(State::Unknown(v)) = &state {
+            unknown(PropertyKind::State, v)?;
+        }
+        if let Some(Error::Unknown(v)) = &error {
+            unknown(PropertyKind::Error, v)?;
+        }
+        for &(kind, ipv4) in &[
+            (PropertyKind::Ipv4, &ipv4),
+            (PropertyKind::Ipv4Configuration, &ipv4_config),
+        ] {
+            if let Some(Ipv4Method::Unknown(v)) = &ipv4.method {
+                unknown(kind, v)?;
+            }
+        }
+        for &(kind, ipv6) in &[
+            (PropertyKind::Ipv6, &ipv6),
+            (PropertyKind::Ipv6Configuration, &ipv6_config),
+        ] {
+            if let Some(Ipv6Method::Unknown(v)) = &ipv6.method {
+                unknown(kind, v)?;
+            }
+            if let Some(Ipv6Privacy::Unknown(v)) = &ipv6.privacy {
+                unknown(kind, v)?;
+            }
+        }
+        for &(kind, proxy) in &[
+            (PropertyKind::Proxy, &proxy),
+            (PropertyKind::ProxyConfiguration, &proxy_config),
+        ] {
+            if let Some(ProxyMethod::Unknown(v)) = &proxy.method {
+                unknown(kind, v)?;
+            }
+        }
+        if let Some(EthernetMethod::Unknown(v)) = &ethernet.method {
+            unknown(PropertyKind::Ethernet, v)?;
+        }
+
+        let warnings = p.into_warnings();
+        let extra = props
+            .into_iter()
+            .filter(|(key, _)| PropertyKind::from_str(key).is_err())
+            .map(|(key, value)| (key, OwnedValue(value)))
+            .collect();
 
         Ok(Properties {
-            state,
+            // Only None in lenient mode, where the problem is already a warning
+            state: state.unwrap_or_else(|| State::Unknown(String::new())),
             error,
             name,
             type_,
@@ -427,11 +1050,30 @@ impl Properties {
             ethernet,
             mdns,
             mdns_config,
+            extra,
+            warnings,
         })
     }
 }
 
+fn parse_security(
+    props: &RefArgMap,
+    prop_name: &'static str,
+) -> Result<Option<Vec<Security>>, PropertyError> {
+    props
+        .get(prop_name)
+        .map(|variant| {
+            super::as_strings(&variant.0)
+                .ok_or(PropertyError::Cast(Cow::Borrowed(prop_name)))?
+                .iter()
+                .map(|s| Security::from_str(s))
+                .collect()
+        })
+        .transpose()
+}
+
 /// Service property fields.
+#[derive(Copy, Clone)]
 enum PropertyKind {
     State,
     Error,
@@ -541,6 +1183,7 @@ pub enum State {
     Ready,
     Disconnect,
     Online,
+    Unknown(String),
 }
 
 impl FromStr for State {
@@ -555,7 +1198,7 @@ impl FromStr for State {
             "ready" => Ok(State::Ready),
             "disconnect" => Ok(State::Disconnect),
             "online" => Ok(State::Online),
-            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string())).into()),
+            _ => Ok(State::Unknown(s.to_string())),
         }
     }
 }
@@ -570,6 +1213,22 @@ pub enum Error {
     LoginFailed,
     AuthFailed,
     InvalidKey,
+    Unknown(String),
+}
+
+impl From<State> for Cow<'static, str> {
+    fn from(state: State) -> Self {
+        match state {
+            State::Idle => Cow::Borrowed("idle"),
+            State::Failure => Cow::Borrowed("failure"),
+            State::Association => Cow::Borrowed("association"),
+            State::Configuration => Cow::Borrowed("configuration"),
+            State::Ready => Cow::Borrowed("ready"),
+            State::Disconnect => Cow::Borrowed("disconnect"),
+            State::Online => Cow::Borrowed("online"),
+            State::Unknown(inner) => Cow::Owned(inner),
+        }
+    }
 }
 
 impl FromStr for Error {
@@ -584,7 +1243,22 @@ impl FromStr for Error {
             "login-failed" => Ok(Error::LoginFailed),
             "auth-failed" => Ok(Error::AuthFailed),
             "invalid-key" => Ok(Error::InvalidKey),
-            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string())).into()),
+            _ => Ok(Error::Unknown(s.to_string())),
+        }
+    }
+}
+
+impl From<Error> for Cow<'static, str> {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::OutOfRange => Cow::Borrowed("out-of-range"),
+            Error::PinMissing => Cow::Borrowed("pin-missing"),
+            Error::DhcpFailed => Cow::Borrowed("dhcp-failed"),
+            Error::ConnectFailed => Cow::Borrowed("connect-failed"),
+            Error::LoginFailed => Cow::Borrowed("login-failed"),
+            Error::AuthFailed => Cow::Borrowed("auth-failed"),
+            Error::InvalidKey => Cow::Borrowed("invalid-key"),
+            Error::Unknown(inner) => Cow::Owned(inner),
         }
     }
 }
@@ -597,6 +1271,16 @@ pub enum Type {
     Unknown(String),
 }
 
+impl From<Type> for Cow<'static, str> {
+    fn from(ty: Type) -> Self {
+        match ty {
+            Type::Wifi => Cow::Borrowed("wifi"),
+            Type::Ethernet => Cow::Borrowed("ethernet"),
+            Type::Unknown(inner) => Cow::Owned(inner),
+        }
+    }
+}
+
 impl FromStr for Type {
     type Err = PropertyError;
 
@@ -609,13 +1293,104 @@ impl FromStr for Type {
     }
 }
 
-/// Ipv4 structure
+/// Service security method.
 #[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Security {
+    None,
+    Wep,
+    Psk,
+    Ieee8021x,
+    Wps,
+    WpsAdvertising,
+    Sae,
+    Owe,
+    Unknown(String),
+}
+
+impl Security {
+    /// Whether connecting requires a passphrase from the user.
+    pub fn requires_passphrase(&self) -> bool {
+        matches!(self, Security::Wep | Security::Psk | Security::Sae)
+    }
+
+    /// Whether this is 802.1x (WPA-Enterprise), which needs an identity and
+    /// EAP credentials instead of a passphrase.
+    pub fn is_enterprise(&self) -> bool {
+        *self == Security::Ieee8021x
+    }
+
+    /// Whether this method is considered insecure.
+    pub fn is_deprecated(&self) -> bool {
+        *self == Security::Wep
+    }
+}
+
+impl From<Security> for Cow<'static, str> {
+    fn from(security: Security) -> Self {
+        match security {
+            Security::None => Cow::Borrowed("none"),
+            Security::Wep => Cow::Borrowed("wep"),
+            Security::Psk => Cow::Borrowed("psk"),
+            Security::Ieee8021x => Cow::Borrowed("ieee8021x"),
+            Security::Wps => Cow::Borrowed("wps"),
+            Security::WpsAdvertising => Cow::Borrowed("wps_advertising"),
+            Security::Sae => Cow::Borrowed("sae"),
+            Security::Owe => Cow::Borrowed("owe"),
+            Security::Unknown(inner) => Cow::Owned(inner),
+        }
+    }
+}
+
+impl FromStr for Security {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Security::None),
+            "wep" => Ok(Security::Wep),
+            "psk" => Ok(Security::Psk),
+            "ieee8021x" => Ok(Security::Ieee8021x),
+            "wps" => Ok(Security::Wps),
+            "wps_advertising" => Ok(Security::WpsAdvertising),
+            "sae" => Ok(Security::Sae),
+            "owe" => Ok(Security::Owe),
+            _ => Ok(Security::Unknown(s.to_string())),
+        }
+    }
+}
+
+/// Ipv4 structure
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Ipv4 {
     pub method: Option<Ipv4Method>,
-    pub address: Option<String>,
-    pub netmask: Option<String>,
-    pub gateway: Option<String>,
+    pub address: Option<Ipv4Addr>,
+    pub netmask: Option<Ipv4Addr>,
+    pub gateway: Option<Ipv4Addr>,
+}
+
+impl Ipv4 {
+    /// Prefix length of the netmask, if set and contiguous.
+    pub fn prefix_length(&self) -> Option<u8> {
+        let mask = u32::from(self.netmask?);
+        let len = (!mask).leading_zeros();
+        if mask.checked_shl(len).unwrap_or(0) == 0 {
+            Some(len as u8)
+        } else {
+            None
+        }
+    }
+
+    /// Address and prefix length, as in `192.168.1.10/24`.
+    pub fn cidr(&self) -> Option<(Ipv4Addr, u8)> {
+        Some((self.address?, self.prefix_length()?))
+    }
+
+    /// Network address, i.e. the address with the netmask applied.
+    pub fn network(&self) -> Option<Ipv4Addr> {
+        Some(Ipv4Addr::from(
+            u32::from(self.address?) & u32::from(self.netmask?),
+        ))
+    }
 }
 
 pub enum Ipv4Kind {
@@ -658,6 +1433,7 @@ pub enum Ipv4Method {
     Auto,
     Off,
     Fixed,
+    Unknown(String),
 }
 
 impl FromStr for Ipv4Method {
@@ -670,18 +1446,31 @@ impl FromStr for Ipv4Method {
             "auto" => Ok(Ipv4Method::Auto),
             "off" => Ok(Ipv4Method::Off),
             "fixed" => Ok(Ipv4Method::Fixed),
-            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+            _ => Ok(Ipv4Method::Unknown(s.to_string())),
+        }
+    }
+}
+
+impl From<Ipv4Method> for Cow<'static, str> {
+    fn from(method: Ipv4Method) -> Self {
+        match method {
+            Ipv4Method::Dhcp => Cow::Borrowed("dhcp"),
+            Ipv4Method::Manual => Cow::Borrowed("manual"),
+            Ipv4Method::Auto => Cow::Borrowed("auto"),
+            Ipv4Method::Off => Cow::Borrowed("off"),
+            Ipv4Method::Fixed => Cow::Borrowed("fixed"),
+            Ipv4Method::Unknown(inner) => Cow::Owned(inner),
         }
     }
 }
 
 /// Ipv6 structure
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Ipv6 {
     pub method: Option<Ipv6Method>,
-    pub address: Option<String>,
+    pub address: Option<Ipv6Addr>,
     pub prefix_length: Option<u8>,
-    pub gateway: Option<String>,
+    pub gateway: Option<Ipv6Addr>,
     pub privacy: Option<Ipv6Privacy>,
 }
 
@@ -727,6 +1516,7 @@ pub enum Ipv6Method {
     Manual,
     SixToFour,
     Off,
+    Unknown(String),
 }
 
 impl FromStr for Ipv6Method {
@@ -738,7 +1528,19 @@ impl FromStr for Ipv6Method {
             "manual" => Ok(Ipv6Method::Manual),
             "6to4" => Ok(Ipv6Method::SixToFour),
             "off" => Ok(Ipv6Method::Off),
-            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+            _ => Ok(Ipv6Method::Unknown(s.to_string())),
+        }
+    }
+}
+
+impl From<Ipv6Method> for Cow<'static, str> {
+    fn from(method: Ipv6Method) -> Self {
+        match method {
+            Ipv6Method::Auto => Cow::Borrowed("auto"),
+            Ipv6Method::Manual => Cow::Borrowed("manual"),
+            Ipv6Method::SixToFour => Cow::Borrowed("6to4"),
+            Ipv6Method::Off => Cow::Borrowed("off"),
+            Ipv6Method::Unknown(inner) => Cow::Owned(inner),
         }
     }
 }
@@ -750,6 +1552,7 @@ pub enum Ipv6Privacy {
     Enabled,
     // The spelling used by connman
     Prefered,
+    Unknown(String),
 }
 
 impl FromStr for Ipv6Privacy {
@@ -761,13 +1564,24 @@ impl FromStr for Ipv6Privacy {
             "enabled" => Ok(Ipv6Privacy::Enabled),
             // The spelling used by connman
             "prefered" => Ok(Ipv6Privacy::Prefered),
-            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+            _ => Ok(Ipv6Privacy::Unknown(s.to_string())),
+        }
+    }
+}
+
+impl From<Ipv6Privacy> for Cow<'static, str> {
+    fn from(privacy: Ipv6Privacy) -> Self {
+        match privacy {
+            Ipv6Privacy::Disabled => Cow::Borrowed("disabled"),
+            Ipv6Privacy::Enabled => Cow::Borrowed("enabled"),
+            Ipv6Privacy::Prefered => Cow::Borrowed("prefered"),
+            Ipv6Privacy::Unknown(inner) => Cow::Owned(inner),
         }
     }
 }
 
 /// Proxy structure
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Proxy {
     pub method: Option<ProxyMethod>,
     pub url: Option<String>,
@@ -788,7 +1602,7 @@ impl FromStr for ProxyKind {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "Method" => Ok(ProxyKind::Method),
-            "Url" => Ok(ProxyKind::Url),
+            "URL" => Ok(ProxyKind::Url),
             "Servers" => Ok(ProxyKind::Servers),
             "Excludes" => Ok(ProxyKind::Excludes),
             _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
@@ -800,7 +1614,7 @@ impl From<ProxyKind> for &'static str {
     fn from(prop: ProxyKind) -> Self {
         match prop {
             ProxyKind::Method => "Method",
-            ProxyKind::Url => "Url",
+            ProxyKind::Url => "URL",
             ProxyKind::Servers => "Servers",
             ProxyKind::Excludes => "Excludes",
         }
@@ -813,6 +1627,7 @@ pub enum ProxyMethod {
     Direct,
     Auto,
     Manual,
+    Unknown(String),
 }
 
 impl FromStr for ProxyMethod {
@@ -823,23 +1638,24 @@ impl FromStr for ProxyMethod {
             "direct" => Ok(ProxyMethod::Direct),
             "auto" => Ok(ProxyMethod::Auto),
             "manual" => Ok(ProxyMethod::Manual),
-            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+            _ => Ok(ProxyMethod::Unknown(s.to_string())),
         }
     }
 }
 
-impl From<ProxyMethod> for &'static str {
+impl From<ProxyMethod> for Cow<'static, str> {
     fn from(method: ProxyMethod) -> Self {
         match method {
-            ProxyMethod::Direct => "direct",
-            ProxyMethod::Auto => "auto",
-            ProxyMethod::Manual => "manual",
+            ProxyMethod::Direct => Cow::Borrowed("direct"),
+            ProxyMethod::Auto => Cow::Borrowed("auto"),
+            ProxyMethod::Manual => Cow::Borrowed("manual"),
+            ProxyMethod::Unknown(inner) => Cow::Owned(inner),
         }
     }
 }
 
 /// Provider structure
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Provider {
     pub host: Option<String>,
     pub domain: Option<String>,
@@ -880,7 +1696,7 @@ impl From<ProviderKind> for &'static str {
 }
 
 /// Provider structure
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Ethernet {
     pub method: Option<EthernetMethod>,
     pub interface: Option<String>,
@@ -928,6 +1744,7 @@ impl From<EthernetKind> for &'static str {
 pub enum EthernetMethod {
     Auto,
     Manual,
+    Unknown(String),
 }
 
 impl FromStr for EthernetMethod {
@@ -937,16 +1754,234 @@ impl FromStr for EthernetMethod {
         match s {
             "auto" => Ok(EthernetMethod::Auto),
             "manual" => Ok(EthernetMethod::Manual),
-            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+            _ => Ok(EthernetMethod::Unknown(s.to_string())),
         }
     }
 }
 
-impl From<EthernetMethod> for &'static str {
+impl From<EthernetMethod> for Cow<'static, str> {
     fn from(method: EthernetMethod) -> Self {
         match method {
-            EthernetMethod::Auto => "auto",
-            EthernetMethod::Manual => "manual",
+            EthernetMethod::Auto => Cow::Borrowed("auto"),
+            EthernetMethod::Manual => Cow::Borrowed("manual"),
+            EthernetMethod::Unknown(inner) => Cow::Owned(inner),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4(address: &str, netmask: &str) -> Ipv4 {
+        Ipv4 {
+            method: None,
+            address: Some(address.parse().unwrap()),
+            netmask: Some(netmask.parse().unwrap()),
+            gateway: None,
+        }
+    }
+
+    #[test]
+    fn ipv4_prefix_length() {
+        let ip = ipv4("192.168.1.10", "255.255.255.0");
+        assert_eq!(ip.prefix_length(), Some(24));
+        assert_eq!(ip.cidr(), Some(("192.168.1.10".parse().unwrap(), 24)));
+        assert_eq!(ip.network(), Some("192.168.1.0".parse().unwrap()));
+    }
+
+    #[test]
+    fn ipv4_prefix_length_bounds() {
+        let ip = ipv4("10.1.2.3", "0.0.0.0");
+        assert_eq!(ip.prefix_length(), Some(0));
+        assert_eq!(ip.network(), Some("0.0.0.0".parse().unwrap()));
+
+        let ip = ipv4("10.1.2.3", "255.255.255.255");
+        assert_eq!(ip.prefix_length(), Some(32));
+        assert_eq!(ip.network(), Some("10.1.2.3".parse().unwrap()));
+    }
+
+    #[test]
+    fn ipv4_non_contiguous_netmask() {
+        let ip = ipv4("10.1.2.3", "255.0.255.0");
+        assert_eq!(ip.prefix_length(), None);
+        assert_eq!(ip.cidr(), None);
+        assert_eq!(ip.network(), Some("10.0.2.0".parse().unwrap()));
+    }
+
+    /// Serializes `value` as the property `Value` and parses it back.
+    fn round_trip<T: IntoProperties + FromProperties>(value: T) -> T {
+        let mut props = RefArgMap::new();
+        insert_property(&mut props, "Value", value.into_properties());
+        T::from_properties(&props, "Value").unwrap()
+    }
+
+    fn properties() -> Properties {
+        Properties {
+            state: State::Online,
+            error: Some(Error::InvalidKey),
+            name: Some("home".to_string()),
+            type_: Some(Type::Wifi),
+            security: Some(vec![Security::Psk, Security::Wps]),
+            strength: Some(70),
+            favorite: true,
+            immutable: false,
+            autoconnect: true,
+            roaming: Some(false),
+            nameservers: vec!["192.168.1.1".parse().unwrap(), "fd00::1".parse().unwrap()],
+            nameservers_config: vec!["1.1.1.1".parse().unwrap()],
+            timeservers: vec!["pool.ntp.org".to_string()],
+            timeservers_config: Vec::new(),
+            domains: vec!["lan".to_string()],
+            domains_config: Vec::new(),
+            ipv4: ipv4("192.168.1.10", "255.255.255.0"),
+            ipv4_config: Ipv4 {
+                method: Some(Ipv4Method::Dhcp),
+                ..Default::default()
+            },
+            ipv6: Ipv6 {
+                method: Some(Ipv6Method::Auto),
+                address: Some("fd00::10".parse().unwrap()),
+                prefix_length: Some(64),
+                gateway: Some("fd00::1".parse().unwrap()),
+                privacy: Some(Ipv6Privacy::Prefered),
+            },
+            ipv6_config: Ipv6::default(),
+            proxy: Proxy {
+                method: Some(ProxyMethod::Manual),
+                url: None,
+                servers: Some(vec!["proxy.lan:3128".to_string()]),
+                excludes: Some(vec!["lan".to_string()]),
+            },
+            proxy_config: Proxy::default(),
+            provider: Provider::default(),
+            ethernet: Ethernet {
+                method: Some(EthernetMethod::Auto),
+                interface: Some("wlan0".to_string()),
+                address: Some("00:11:22:33:44:55".to_string()),
+                mtu: Some(1500),
+            },
+            mdns: Some(false),
+            mdns_config: None,
+            extra: HashMap::new(),
+            warnings: Vec::new(),
         }
     }
+
+    #[test]
+    fn properties_round_trip() {
+        let props = properties();
+        assert_eq!(
+            Properties::try_from(props.clone().into_properties()),
+            Ok(props)
+        );
+    }
+
+    #[test]
+    fn properties_round_trip_unknown() {
+        let mut props = properties();
+        props.state = State::Unknown("waiting".to_string());
+        props.error = Some(Error::Unknown("new-error".to_string()));
+        props.type_ = Some(Type::Unknown("cellular".to_string()));
+        props.security = Some(vec![Security::Unknown("wpa4".to_string())]);
+        props.ipv4.method = Some(Ipv4Method::Unknown("link-local".to_string()));
+        props.ipv6.method = Some(Ipv6Method::Unknown("slaac".to_string()));
+        props.ipv6.privacy = Some(Ipv6Privacy::Unknown("stable".to_string()));
+        props.proxy.method = Some(ProxyMethod::Unknown("pac".to_string()));
+        props.ethernet.method = Some(EthernetMethod::Unknown("static".to_string()));
+        props
+            .extra
+            .insert("Future".to_string(), OwnedValue(Variant(Box::new(7u32))));
+
+        let mut parsed =
+            Properties::parse(props.clone().into_properties(), ParseMode::Lenient).unwrap();
+        // One warning per unknown value
+        assert_eq!(parsed.warnings.len(), 9);
+        parsed.warnings.clear();
+        assert_eq!(parsed, props);
+    }
+
+    #[test]
+    fn ipv4_round_trip() {
+        let ip = Ipv4 {
+            method: Some(Ipv4Method::Manual),
+            address: Some("10.0.0.2".parse().unwrap()),
+            netmask: Some("255.0.0.0".parse().unwrap()),
+            gateway: Some("10.0.0.1".parse().unwrap()),
+        };
+        assert_eq!(round_trip(ip.clone()), ip);
+
+        let ip = Ipv4 {
+            method: Some(Ipv4Method::Unknown("link-local".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(round_trip(ip.clone()), ip);
+    }
+
+    #[test]
+    fn ipv6_round_trip() {
+        let ip = Ipv6 {
+            method: Some(Ipv6Method::Manual),
+            address: Some("2001:db8::2".parse().unwrap()),
+            prefix_length: Some(48),
+            gateway: Some("2001:db8::1".parse().unwrap()),
+            privacy: Some(Ipv6Privacy::Enabled),
+        };
+        assert_eq!(round_trip(ip.clone()), ip);
+
+        let ip = Ipv6 {
+            method: Some(Ipv6Method::Unknown("slaac".to_string())),
+            privacy: Some(Ipv6Privacy::Unknown("stable".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(round_trip(ip.clone()), ip);
+    }
+
+    #[test]
+    fn proxy_round_trip() {
+        let proxy = Proxy {
+            method: Some(ProxyMethod::Auto),
+            url: Some("http://wpad.lan/proxy.pac".to_string()),
+            servers: None,
+            excludes: None,
+        };
+        assert_eq!(round_trip(proxy.clone()), proxy);
+
+        let proxy = Proxy {
+            method: Some(ProxyMethod::Unknown("pac".to_string())),
+            url: None,
+            servers: Some(vec!["a:1".to_string(), "b:2".to_string()]),
+            excludes: Some(Vec::new()),
+        };
+        assert_eq!(round_trip(proxy.clone()), proxy);
+    }
+
+    #[test]
+    fn ethernet_round_trip() {
+        let ethernet = Ethernet {
+            method: Some(EthernetMethod::Manual),
+            interface: Some("eth0".to_string()),
+            address: Some("00:11:22:33:44:55".to_string()),
+            mtu: Some(9000),
+        };
+        assert_eq!(round_trip(ethernet.clone()), ethernet);
+
+        let ethernet = Ethernet {
+            method: Some(EthernetMethod::Unknown("static".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(round_trip(ethernet.clone()), ethernet);
+    }
+
+    #[test]
+    fn provider_round_trip() {
+        let provider = Provider {
+            host: Some("vpn.example.com".to_string()),
+            domain: Some("example.com".to_string()),
+            name: Some("work".to_string()),
+            type_: Some("openvpn".to_string()),
+        };
+        assert_eq!(round_trip(provider.clone()), provider);
+        assert_eq!(round_trip(Provider::default()), Provider::default());
+    }
 }
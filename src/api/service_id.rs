@@ -0,0 +1,234 @@
+//! Identifiers encoded in connman service object paths
+//!
+//! Connman names services `/net/connman/service/<type>_<ident>`, where the
+//! layout of `<ident>` depends on the service type.
+
+use super::service::Security;
+use super::PropertyError;
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+const SERVICE_PATH_PREFIX: &str = "/net/connman/service/";
+
+/// Parsed service identifier.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ServiceId {
+    /// `wifi_<mac>_<hexssid>_<mode>_<security>`
+    Wifi {
+        mac: MacAddr,
+        /// Raw SSID bytes; empty for hidden networks
+        ssid: Vec<u8>,
+        mode: WifiMode,
+        security: Security,
+    },
+    /// `ethernet_<mac>_cable`
+    Ethernet { mac: MacAddr },
+    /// `vpn_<host>`, with non-alphanumeric characters of the host replaced by `_`
+    Vpn { host: String },
+    /// `bluetooth_<mac>_<remote mac>`
+    Bluetooth { mac: MacAddr, remote: MacAddr },
+    /// Any other `<type>_<ident>`
+    Other { type_: String, ident: String },
+}
+
+impl ServiceId {
+    /// Parse the identifier from a service object path.
+    pub fn from_path(path: &dbus::Path) -> Result<Self, PropertyError> {
+        let path: &str = path;
+        let name = path
+            .strip_prefix(SERVICE_PATH_PREFIX)
+            .ok_or_else(|| cast_err(path))?;
+        name.parse()
+    }
+
+    /// SSID as a string, if this is a wifi service with a UTF-8 SSID.
+    pub fn ssid_str(&self) -> Option<&str> {
+        match self {
+            ServiceId::Wifi { ssid, .. } => std::str::from_utf8(ssid).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ServiceId {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('_');
+        let type_ = parts.next().unwrap_or_default();
+        let rest: Vec<&str> = parts.collect();
+
+        match (type_, rest.as_slice()) {
+            ("wifi", [mac, ssid, mode, security]) => Ok(ServiceId::Wifi {
+                mac: mac.parse().map_err(|_| cast_err(s))?,
+                ssid: match *ssid {
+                    "hidden" => Vec::new(),
+                    hex => decode_hex(hex).ok_or_else(|| cast_err(s))?,
+                },
+                mode: mode.parse().map_err(|_| cast_err(s))?,
+                security: security.parse().map_err(|_| cast_err(s))?,
+            }),
+            ("ethernet", [mac, "cable"]) => Ok(ServiceId::Ethernet {
+                mac: mac.parse().map_err(|_| cast_err(s))?,
+            }),
+            ("vpn", [_, ..]) => Ok(ServiceId::Vpn {
+                host: rest.join("_"),
+            }),
+            ("bluetooth", [mac, remote]) => Ok(ServiceId::Bluetooth {
+                mac: mac.parse().map_err(|_| cast_err(s))?,
+                remote: remote.parse().map_err(|_| cast_err(s))?,
+            }),
+            ("wifi", _) | ("ethernet", _) | ("bluetooth", _) | ("", _) | (_, []) => {
+                Err(cast_err(s))
+            }
+            (type_, _) => Ok(ServiceId::Other {
+                type_: type_.to_string(),
+                ident: rest.join("_"),
+            }),
+        }
+    }
+}
+
+/// Wifi network mode.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WifiMode {
+    Managed,
+    Adhoc,
+    Hidden,
+}
+
+impl FromStr for WifiMode {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "managed" => Ok(WifiMode::Managed),
+            "adhoc" => Ok(WifiMode::Adhoc),
+            "hidden" => Ok(WifiMode::Hidden),
+            _ => Err(cast_err(s)),
+        }
+    }
+}
+
+impl From<WifiMode> for &'static str {
+    fn from(mode: WifiMode) -> Self {
+        match mode {
+            WifiMode::Managed => "managed",
+            WifiMode::Adhoc => "adhoc",
+            WifiMode::Hidden => "hidden",
+        }
+    }
+}
+
+/// Hardware address as encoded in service paths (12 hex digits).
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MacAddr(pub [u8; 6]);
+
+impl FromStr for MacAddr {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = decode_hex(s).ok_or_else(|| cast_err(s))?;
+        let mut addr = [0u8; 6];
+        if bytes.len() != addr.len() {
+            return Err(cast_err(s));
+        }
+        addr.copy_from_slice(&bytes);
+        Ok(MacAddr(addr))
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            a, b, c, d, e, g
+        )
+    }
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [hi, lo] => std::str::from_utf8(&[*hi, *lo])
+                .ok()
+                .and_then(|b| u8::from_str_radix(b, 16).ok()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn cast_err(s: &str) -> PropertyError {
+    PropertyError::Cast(Cow::Owned(s.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAC: MacAddr = MacAddr([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+
+    #[test]
+    fn wifi() {
+        let id: ServiceId = "wifi_001122334455_6d79_managed_psk".parse().unwrap();
+        assert_eq!(
+            id,
+            ServiceId::Wifi {
+                mac: MAC,
+                ssid: b"my".to_vec(),
+                mode: WifiMode::Managed,
+                security: Security::Psk,
+            }
+        );
+        assert_eq!(id.ssid_str(), Some("my"));
+    }
+
+    #[test]
+    fn wifi_hidden_ssid() {
+        let id: ServiceId = "wifi_001122334455_hidden_managed_none".parse().unwrap();
+        assert_eq!(
+            id,
+            ServiceId::Wifi {
+                mac: MAC,
+                ssid: Vec::new(),
+                mode: WifiMode::Managed,
+                security: Security::None,
+            }
+        );
+    }
+
+    #[test]
+    fn wifi_odd_length_ssid() {
+        assert!("wifi_001122334455_6d7_managed_psk"
+            .parse::<ServiceId>()
+            .is_err());
+    }
+
+    #[test]
+    fn vpn_host_with_underscores() {
+        let id: ServiceId = "vpn_vpn_example_com".parse().unwrap();
+        assert_eq!(
+            id,
+            ServiceId::Vpn {
+                host: "vpn_example_com".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn from_path() {
+        let path = dbus::Path::new("/net/connman/service/ethernet_001122334455_cable").unwrap();
+        assert_eq!(
+            ServiceId::from_path(&path).ok(),
+            Some(ServiceId::Ethernet { mac: MAC })
+        );
+
+        let path = dbus::Path::new("/net/connman/technology/wifi").unwrap();
+        assert!(ServiceId::from_path(&path).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Signal-maintained cache of the connman service list
+
+use dbus::nonblock::SyncConnection;
+use futures::future::FutureExt;
+use futures::stream::{Stream, StreamExt};
+
+use super::gen::manager::Manager as IManager;
+use super::manager::{Manager, ManagerEvent};
+use super::retry::RetryPolicy;
+use super::service::{Properties, Service};
+use super::signal::SignalStream;
+use super::{clone_properties, Error, ParseMode, PropertyError, RefArgMap};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Change applied to a `ServiceList`.
+#[derive(Clone)]
+pub enum ServiceDiff {
+    /// A new service appeared
+    Added(Service<Arc<SyncConnection>>),
+    /// One or more properties of a known service changed
+    Updated(Service<Arc<SyncConnection>>),
+    /// A service is no longer available; also sent for services only ever
+    /// reported as `ParseFailed`
+    Removed(dbus::Path<'static>),
+    /// The relative order of the services changed; holds the new order
+    Reordered(Vec<dbus::Path<'static>>),
+    /// The properties of a service, merged with the latest change, failed to
+    /// parse. The service is left out of the list, or dropped from it, until a
+    /// later change makes it parse, which is reported as `Added`.
+    ParseFailed(dbus::Path<'static>, PropertyError),
+}
+
+struct Entry {
+    path: dbus::Path<'static>,
+    service: Result<Service<Arc<SyncConnection>>, PropertyError>,
+    // Every property seen so far, which `ServicesChanged` only updates
+    // piecemeal.
+    raw: RefArgMap,
+}
+
+/// In-memory copy of every connman service, kept current by the Manager
+/// `ServicesChanged` signal.
+///
+/// The list only advances while it is being polled as a `Stream`, which
+/// yields a `ServiceDiff` for every change applied to it. Services whose
+/// properties fail to parse are kept out of the list, but their properties are
+/// still tracked so that a later change can complete them.
+///
+/// The list holds a `Manager::events()` stream; see `SignalStream` on using it
+/// alongside other streams.
+pub struct ServiceList {
+    connection: Arc<SyncConnection>,
+    timeout: Duration,
+    parse_mode: ParseMode,
+    retry: RetryPolicy,
+    entries: Vec<Entry>,
+    events: SignalStream<ManagerEvent>,
+    pending: VecDeque<ServiceDiff>,
+}
+
+impl ServiceList {
+    pub async fn new(manager: &Manager<Arc<SyncConnection>>) -> Result<Self, Error> {
+        // Subscribe first so no change is lost between the two calls.
+        let mut events = manager.events().await?;
+        let connection = manager.proxy.connection.clone();
+        let timeout = manager.timeout;
+        let parse_mode = manager.parse_mode;
+        let retry = manager.retry.clone();
+
+        // Changes queued by the time the reply arrives may predate it, and
+        // applying them on top of it would roll services back. Fetch again
+        // until none came in; any change queued later is newer than the reply.
+        let services = loop {
+            let services = retry.run(|| IManager::get_services(&manager.proxy)).await?;
+            let mut stale = false;
+            while let Some(Some(event)) = events.next().now_or_never() {
+                stale |= matches!(event, ManagerEvent::ServicesChanged { .. });
+            }
+            if !stale {
+                break services;
+            }
+        };
+
+        let mut list = ServiceList {
+            connection,
+            timeout,
+            parse_mode,
+            retry,
+            entries: Vec::with_capacity(services.len()),
+            events,
+            pending: VecDeque::new(),
+        };
+        // Report the services that failed to parse on the first poll.
+        for (path, raw) in services {
+            let service = list.parse(&path, &raw);
+            if let Err(e) = &service {
+                list.pending
+                    .push_back(ServiceDiff::ParseFailed(path.clone(), e.clone()));
+            }
+            list.entries.push(Entry { path, service, raw });
+        }
+        Ok(list)
+    }
+
+    /// Current services, in the order given by connman.
+    pub fn snapshot(&self) -> Vec<Service<Arc<SyncConnection>>> {
+        self.services().cloned().collect()
+    }
+
+    pub fn get(&self, path: &dbus::Path) -> Option<&Service<Arc<SyncConnection>>> {
+        self.services().find(|service| service.path() == path)
+    }
+
+    fn services(&self) -> impl Iterator<Item = &Service<Arc<SyncConnection>>> {
+        self.entries.iter().filter_map(|e| e.service.as_ref().ok())
+    }
+
+    fn parse(
+        &self,
+        path: &dbus::Path<'static>,
+        raw: &RefArgMap,
+    ) -> Result<Service<Arc<SyncConnection>>, PropertyError> {
+        Service::try_new(
+            self.connection.clone(),
+            path.clone(),
+            clone_properties(raw),
+            self.timeout,
+            self.parse_mode,
+            self.retry.clone(),
+        )
+    }
+
+    fn apply(
+        &mut self,
+        changed: Vec<(dbus::Path<'static>, RefArgMap)>,
+        removed: Vec<dbus::Path<'static>>,
+    ) {
+        let old_paths: Vec<dbus::Path<'static>> =
+            self.entries.iter().map(|e| e.path.clone()).collect();
+        let old_order: Vec<dbus::Path<'static>> =
+            self.services().map(|s| s.path().clone()).collect();
+        let mut old: HashMap<dbus::Path<'static>, Entry> = self
+            .entries
+            .drain(..)
+            .map(|e| (e.path.clone(), e))
+            .collect();
+
+        for path in removed {
+            if old.remove(&path).is_some() {
+                self.pending.push_back(ServiceDiff::Removed(path));
+            }
+        }
+
+        // `changed` lists every registered service in order, but only carries
+        // the properties that changed since the last signal.
+        let mut entries = Vec::with_capacity(changed.len());
+        for (path, props) in changed {
+            match old.remove(&path) {
+                Some(mut entry) => {
+                    if !props.is_empty() {
+                        entry.raw.extend(props);
+                        let parsed =
+                            Properties::parse(clone_properties(&entry.raw), self.parse_mode);
+                        let diff = match (&mut entry.service, parsed) {
+                            (Ok(service), Ok(props)) => {
+                                service.props = props;
+                                ServiceDiff::Updated(service.clone())
+                            }
+                            (Err(_), Ok(_)) => {
+                                entry.service = self.parse(&entry.path, &entry.raw);
+                                match &entry.service {
+                                    Ok(service) => ServiceDiff::Added(service.clone()),
+                                    Err(e) => ServiceDiff::ParseFailed(path, e.clone()),
+                                }
+                            }
+                            (_, Err(e)) => {
+                                entry.service = Err(e.clone());
+                                ServiceDiff::ParseFailed(path, e)
+                            }
+                        };
+                        self.pending.push_back(diff);
+                    }
+                    entries.push(entry);
+                }
+                None => {
+                    let service = self.parse(&path, &props);
+                    self.pending.push_back(match &service {
+                        Ok(service) => ServiceDiff::Added(service.clone()),
+                        Err(e) => ServiceDiff::ParseFailed(path.clone(), e.clone()),
+                    });
+                    entries.push(Entry {
+                        path,
+                        service,
+                        raw: props,
+                    });
+                }
+            }
+        }
+
+        // Anything left over was dropped from the list without being removed
+        // explicitly.
+        for path in old_paths.iter().filter(|p| old.contains_key(*p)) {
+            self.pending.push_back(ServiceDiff::Removed(path.clone()));
+        }
+
+        let new_order: Vec<dbus::Path<'static>> = entries
+            .iter()
+            .filter(|e| e.service.is_ok())
+            .map(|e| e.path.clone())
+            .collect();
+        let kept_after: HashSet<&dbus::Path<'static>> = new_order.iter().collect();
+        let kept_before: HashSet<&dbus::Path<'static>> = old_order.iter().collect();
+        let before = old_order.iter().filter(|p| kept_after.contains(p));
+        let after = new_order.iter().filter(|p| kept_before.contains(p));
+        if !before.eq(after) {
+            self.pending.push_back(ServiceDiff::Reordered(new_order));
+        }
+
+        self.entries = entries;
+    }
+}
+
+impl Stream for ServiceList {
+    type Item = ServiceDiff;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(diff) = self.pending.pop_front() {
+                return Poll::Ready(Some(diff));
+            }
+
+            match self.events.poll_next_unpin(cx) {
+                Poll::Ready(Some(ManagerEvent::ServicesChanged { changed, removed })) => {
+                    self.apply(changed, removed)
+                }
+                Poll::Ready(Some(_)) => {}
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Per-application sessions (`net.connman.Session`)
+
+use dbus::arg::{RefArg, Variant};
+use dbus::nonblock::{Proxy, SyncConnection};
+use dbus::Message;
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::stream::{Stream, StreamExt};
+
+use super::export::{call_no_reply, error_reply, invalid_args, unique_path, Exported, Reply};
+use super::gen::manager::Manager as IManager;
+use super::gen::session::Session as ISession;
+use super::service::{Ipv4, Ipv6};
+use super::{Error, FromProperties, PropertyError, RefArgMap};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+const NOTIFICATION_INTERFACE: &str = "net.connman.Notification";
+
+/// Settings an application can request for its session.
+///
+/// Unset fields are left at connman's default (or current value, for
+/// `Session::change`).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SessionSettings {
+    /// Service types the session may use, in order of preference; `"*"`
+    /// allows any
+    pub allowed_bearers: Option<Vec<String>>,
+    pub connection_type: Option<ConnectionType>,
+    /// Restrict the session to a single network interface
+    pub allowed_interface: Option<String>,
+    /// Route the session's traffic by source address
+    pub source_ip_rule: Option<bool>,
+    /// Identifier used to match the session against a policy file
+    pub context_identifier: Option<String>,
+}
+
+impl SessionSettings {
+    fn into_dict(self) -> HashMap<&'static str, Variant<Box<dyn RefArg>>> {
+        let mut dict = HashMap::new();
+        let mut insert = |kind: SessionKind, value: Option<Box<dyn RefArg>>| {
+            if let Some(value) = value {
+                dict.insert(kind.into(), Variant(value));
+            }
+        };
+
+        insert(
+            SessionKind::AllowedBearers,
+            self.allowed_bearers.map(|v| Box::new(v) as _),
+        );
+        insert(
+            SessionKind::ConnectionType,
+            self.connection_type
+                .map(|v| Box::new(<&str>::from(v).to_string()) as _),
+        );
+        insert(
+            SessionKind::AllowedInterface,
+            self.allowed_interface.map(|v| Box::new(v) as _),
+        );
+        insert(
+            SessionKind::SourceIpRule,
+            self.source_ip_rule.map(|v| Box::new(v) as _),
+        );
+        insert(
+            SessionKind::ContextIdentifier,
+            self.context_identifier.map(|v| Box::new(v) as _),
+        );
+        dict
+    }
+}
+
+/// Session settings reported by connman through the notifier.
+///
+/// Only the settings that changed since the previous update are set.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SessionUpdate {
+    pub state: Option<SessionState>,
+    /// Name of the service in use
+    pub name: Option<String>,
+    /// Type of the service in use
+    pub bearer: Option<String>,
+    /// Network interface in use
+    pub interface: Option<String>,
+    pub ipv4: Option<Ipv4>,
+    pub ipv6: Option<Ipv6>,
+    pub allowed_bearers: Option<Vec<String>>,
+    pub connection_type: Option<ConnectionType>,
+    pub allowed_interface: Option<String>,
+    pub source_ip_rule: Option<bool>,
+    pub context_identifier: Option<String>,
+}
+
+impl SessionUpdate {
+    pub fn try_from(props: RefArgMap) -> Result<Self, PropertyError> {
+        Ok(SessionUpdate {
+            state: FromProperties::from_properties(&props, SessionKind::State.into())?,
+            name: FromProperties::from_properties(&props, SessionKind::Name.into())?,
+            bearer: FromProperties::from_properties(&props, SessionKind::Bearer.into())?,
+            interface: FromProperties::from_properties(&props, SessionKind::Interface.into())?,
+            ipv4: FromProperties::from_properties(&props, SessionKind::Ipv4.into())?,
+            ipv6: FromProperties::from_properties(&props, SessionKind::Ipv6.into())?,
+            allowed_bearers: FromProperties::from_properties(
+                &props,
+                SessionKind::AllowedBearers.into(),
+            )?,
+            connection_type: FromProperties::from_properties(
+                &props,
+                SessionKind::ConnectionType.into(),
+            )?,
+            allowed_interface: FromProperties::from_properties(
+                &props,
+                SessionKind::AllowedInterface.into(),
+            )?,
+            source_ip_rule: FromProperties::from_properties(
+                &props,
+                SessionKind::SourceIpRule.into(),
+            )?,
+            context_identifier: FromProperties::from_properties(
+                &props,
+                SessionKind::ContextIdentifier.into(),
+            )?,
+        })
+    }
+}
+
+/// Handle to a session created with the connman Manager.
+///
+/// Yields a `SessionUpdate` whenever connman changes the session settings.
+/// The session is destroyed when this is dropped.
+pub struct Session {
+    proxy: Proxy<'static, Arc<SyncConnection>>,
+    manager: Option<Proxy<'static, Arc<SyncConnection>>>,
+    inner: UnboundedReceiver<SessionUpdate>,
+    _exported: Exported,
+}
+
+impl Session {
+    pub(crate) async fn new(
+        manager: Proxy<'static, Arc<SyncConnection>>,
+        settings: SessionSettings,
+    ) -> Result<Self, Error> {
+        let notifier = unique_path("notifier");
+        let (sender, inner) = mpsc::unbounded();
+
+        // The notifier has to exist before the session, which sends its first
+        // update right away.
+        let exported = Exported::new(
+            &manager.connection,
+            notifier.clone(),
+            NOTIFICATION_INTERFACE,
+            move |msg| {
+                let reply = dispatch(&sender, &msg);
+                Box::pin(async move { reply }) as Reply
+            },
+        );
+
+        let path =
+            IManager::create_session(&manager, settings.into_dict(), notifier.clone()).await?;
+
+        Ok(Session {
+            proxy: Proxy::new(
+                "net.connman",
+                path,
+                manager.timeout,
+                manager.connection.clone(),
+            ),
+            manager: Some(manager),
+            inner,
+            _exported: exported,
+        })
+    }
+
+    pub fn path(&self) -> &dbus::Path<'static> {
+        &self.proxy.path
+    }
+
+    pub async fn connect(&self) -> Result<(), Error> {
+        Ok(ISession::connect(&self.proxy).await?)
+    }
+
+    pub async fn disconnect(&self) -> Result<(), Error> {
+        Ok(ISession::disconnect(&self.proxy).await?)
+    }
+
+    /// Change every setting that is set in `settings`.
+    pub async fn change(&self, settings: SessionSettings) -> Result<(), Error> {
+        if let Some(bearers) = settings.allowed_bearers {
+            ISession::change(&self.proxy, SessionKind::AllowedBearers.into(), bearers).await?;
+        }
+        if let Some(connection_type) = settings.connection_type {
+            let connection_type: &'static str = connection_type.into();
+            ISession::change(
+                &self.proxy,
+                SessionKind::ConnectionType.into(),
+                connection_type,
+            )
+            .await?;
+        }
+        if let Some(iface) = settings.allowed_interface {
+            ISession::change(&self.proxy, SessionKind::AllowedInterface.into(), iface).await?;
+        }
+        if let Some(rule) = settings.source_ip_rule {
+            ISession::change(&self.proxy, SessionKind::SourceIpRule.into(), rule).await?;
+        }
+        if let Some(id) = settings.context_identifier {
+            ISession::change(&self.proxy, SessionKind::ContextIdentifier.into(), id).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn destroy(mut self) -> Result<(), Error> {
+        match self.manager.take() {
+            Some(manager) => Ok(IManager::destroy_session(&manager, self.path().clone()).await?),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Stream for Session {
+    type Item = SessionUpdate;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if let Some(manager) = self.manager.take() {
+            call_no_reply(
+                &manager.connection,
+                "net.connman",
+                "/".into(),
+                "net.connman.Manager",
+                "DestroySession",
+                (self.path().clone(),),
+            );
+        }
+    }
+}
+
+fn dispatch(sender: &UnboundedSender<SessionUpdate>, msg: &Message) -> Message {
+    match msg.member().as_deref() {
+        Some("Update") => {
+            let update = match msg.read1::<RefArgMap>() {
+                Ok(props) => SessionUpdate::try_from(props),
+                Err(_) => return invalid_args(msg),
+            };
+            match update {
+                Ok(update) => {
+                    let _ = sender.unbounded_send(update);
+                    msg.method_return()
+                }
+                Err(e) => error_reply(
+                    msg,
+                    "org.freedesktop.DBus.Error.InvalidArgs",
+                    &e.to_string(),
+                ),
+            }
+        }
+        Some("Release") => {
+            sender.close_channel();
+            msg.method_return()
+        }
+        _ => error_reply(
+            msg,
+            "org.freedesktop.DBus.Error.UnknownMethod",
+            "Unknown method",
+        ),
+    }
+}
+
+/// Session setting fields.
+#[derive(Copy, Clone, Debug)]
+pub enum SessionKind {
+    State,
+    Name,
+    Bearer,
+    Interface,
+    Ipv4,
+    Ipv6,
+    AllowedBearers,
+    ConnectionType,
+    AllowedInterface,
+    SourceIpRule,
+    ContextIdentifier,
+}
+
+impl From<SessionKind> for &'static str {
+    fn from(kind: SessionKind) -> Self {
+        match kind {
+            SessionKind::State => "State",
+            SessionKind::Name => "Name",
+            SessionKind::Bearer => "Bearer",
+            SessionKind::Interface => "Interface",
+            SessionKind::Ipv4 => "IPv4",
+            SessionKind::Ipv6 => "IPv6",
+            SessionKind::AllowedBearers => "AllowedBearers",
+            SessionKind::ConnectionType => "ConnectionType",
+            SessionKind::AllowedInterface => "AllowedInterface",
+            SessionKind::SourceIpRule => "SourceIPRule",
+            SessionKind::ContextIdentifier => "ContextIdentifier",
+        }
+    }
+}
+
+/// Session connection state.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SessionState {
+    Disconnected,
+    Connected,
+    Online,
+}
+
+impl FromStr for SessionState {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disconnected" => Ok(SessionState::Disconnected),
+            "connected" => Ok(SessionState::Connected),
+            "online" => Ok(SessionState::Online),
+            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+        }
+    }
+}
+
+impl FromProperties for SessionState {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        super::get_property_fromstr::<Self>(properties, prop_name)
+    }
+}
+
+/// Kind of connectivity a session asks for.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ConnectionType {
+    Any,
+    /// Local network only
+    Local,
+    /// Internet connectivity
+    Internet,
+}
+
+impl FromStr for ConnectionType {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(ConnectionType::Any),
+            "local" => Ok(ConnectionType::Local),
+            "internet" => Ok(ConnectionType::Internet),
+            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+        }
+    }
+}
+
+impl From<ConnectionType> for &'static str {
+    fn from(ty: ConnectionType) -> Self {
+        match ty {
+            ConnectionType::Any => "any",
+            ConnectionType::Local => "local",
+            ConnectionType::Internet => "internet",
+        }
+    }
+}
+
+impl FromProperties for ConnectionType {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        super::get_property_fromstr::<Self>(properties, prop_name)
+    }
+}
@@ -0,0 +1,102 @@
+//! D-Bus signal subscription helpers
+
+use dbus::channel::{MatchingReceiver, Token};
+use dbus::message::MatchRule;
+use dbus::nonblock::SyncConnection;
+use dbus::Message;
+use futures::channel::mpsc::UnboundedReceiver;
+use futures::stream::{Stream, StreamExt};
+
+use super::export::call_no_reply;
+use super::retry::RetryPolicy;
+use super::Error;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+type ParseFn<T> = Box<dyn FnMut(Message) -> Option<T> + Send>;
+
+/// Stream of typed signals received for a single match rule.
+///
+/// The match rule is registered with the bus when the stream is created, and
+/// removed again when the stream is dropped.
+///
+/// By default dbus-rs hands each signal only to the first matching filter, so
+/// streams with overlapping rules split the signals between them. This
+/// happens with two `Manager::events()` streams, with `Manager::events()` and
+/// a `ServiceList`, with `Technology::scan_services()` while either is held,
+/// and with `Service::connect_and_wait()` while `Service::events()` is held.
+/// To use them together, turn on `SyncConnection::set_signal_match_mode(true)`
+/// when setting up the connection. This crate doesn't do so itself, as the
+/// mode applies to every match on the connection, including the caller's own.
+pub struct SignalStream<T> {
+    connection: Arc<SyncConnection>,
+    token: Token,
+    match_str: String,
+    inner: UnboundedReceiver<Message>,
+    parse: ParseFn<T>,
+}
+
+impl<T> SignalStream<T> {
+    /// Registers `rule` on `connection`, retrying the `AddMatch` call under
+    /// `retry`, and yields every matching message that `parse` turns into a
+    /// `T`.
+    pub(crate) async fn new<F>(
+        connection: Arc<SyncConnection>,
+        rule: MatchRule<'static>,
+        retry: &RetryPolicy,
+        parse: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnMut(Message) -> Option<T> + Send + 'static,
+    {
+        let match_str = rule.match_str();
+        let (msg_match, inner) = retry
+            .run(|| connection.add_match(rule.clone()))
+            .await?
+            .msg_stream();
+
+        Ok(SignalStream {
+            connection,
+            token: msg_match.token(),
+            match_str,
+            inner,
+            parse: Box::new(parse),
+        })
+    }
+}
+
+impl<T> Stream for SignalStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(msg)) => {
+                    if let Some(item) = (self.parse)(msg) {
+                        return Poll::Ready(Some(item));
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T> Unpin for SignalStream<T> {}
+
+impl<T> Drop for SignalStream<T> {
+    fn drop(&mut self) {
+        let _ = self.connection.stop_receive(self.token);
+
+        call_no_reply(
+            &self.connection,
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus".into(),
+            "org.freedesktop.DBus",
+            "RemoveMatch",
+            (self.match_str.as_str(),),
+        );
+    }
+}
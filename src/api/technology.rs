@@ -4,23 +4,36 @@ use std::sync::Arc;
 
 use std::collections::HashMap;
 
+use futures::stream::StreamExt;
+
 use super::gen::technology::Technology as ITechnology;
-use super::{Error as ApiError, RefArgMap};
-use crate::api::{FromProperties, PropertyError};
+use super::manager::{Manager, ManagerEvent};
+use super::retry::RetryPolicy;
+use super::service::Service;
+use super::{Error as ApiError, ParseMode, RefArgMap};
+use crate::api::{
+    insert_property, insert_property_opt, FromProperties, IntoProperties, PropertyError,
+};
 use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::ops::Deref;
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[cfg(feature = "introspection")]
 use xml::reader::EventReader;
 
+/// Quiet period after which `scan_services` considers the service list settled.
+const SCAN_SETTLE_TIME: Duration = Duration::from_millis(500);
+
 /// Futures-aware wrapper struct for connman Technology object.
 #[derive(Clone)]
 pub struct Technology<C> {
     proxy: Proxy<'static, C>,
     pub props: Properties,
+    // Passed on to the services returned by `scan_services`
+    parse_mode: ParseMode,
+    retry: RetryPolicy,
 }
 
 impl<C> Technology<C> {
@@ -30,12 +43,37 @@ impl<C> Technology<C> {
         args: RefArgMap,
         timeout: Duration,
     ) -> Result<Self, ApiError> {
-        Properties::try_from(args)
-            .map_err(ApiError::from)
-            .map(|props| Technology {
-                proxy: Self::proxy(path, timeout, connection),
-                props,
-            })
+        Ok(Self::try_new(
+            connection,
+            path,
+            args,
+            timeout,
+            ParseMode::Strict,
+            RetryPolicy::default(),
+        )?)
+    }
+
+    /// `mode` is only used for the services returned by `scan_services`.
+    pub(crate) fn try_new(
+        connection: C,
+        path: dbus::Path<'static>,
+        args: RefArgMap,
+        timeout: Duration,
+        mode: ParseMode,
+        retry: RetryPolicy,
+    ) -> Result<Self, PropertyError> {
+        Properties::try_from(args).map(|props| Technology {
+            proxy: Self::proxy(path, timeout, connection),
+            props,
+            parse_mode: mode,
+            retry,
+        })
+    }
+
+    /// Set the policy for retrying failed calls.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
     }
 
     pub fn proxy(path: dbus::Path<'static>, timeout: Duration, conn: C) -> Proxy<'static, C> {
@@ -53,23 +91,99 @@ impl<T: NonblockReply, C: Deref<Target = T>> Technology<C> {
     pub async fn introspect(&self) -> Result<EventReader<std::io::Cursor<Vec<u8>>>, ApiError> {
         use crate::api::gen::technology::OrgFreedesktopDBusIntrospectable as Introspectable;
 
-        let s = Introspectable::introspect(&self.proxy).await?;
+        let s = self
+            .retry
+            .run(|| Introspectable::introspect(&self.proxy))
+            .await?;
         let rdr = std::io::Cursor::new(s.into_bytes());
         Ok(EventReader::new(rdr))
     }
 
     pub async fn scan(&self) -> Result<(), ApiError> {
-        Ok(ITechnology::scan(&self.proxy).await?)
+        self.retry.run(|| ITechnology::scan(&self.proxy)).await
+    }
+}
+
+impl Technology<Arc<SyncConnection>> {
+    /// Scan, then return the services of this technology's type in connman's
+    /// order.
+    ///
+    /// After the scan completes, this waits until no `ServicesChanged` signal
+    /// has arrived for 500ms, for at most `timeout` overall. If the caller
+    /// holds a `Manager::events()` stream or a `ServiceList` on the same
+    /// connection, signal match mode must be on; see `SignalStream`.
+    ///
+    /// If the scan changed nothing, no signal arrives and the list is returned
+    /// after the 500ms wait. If services are still changing when `timeout`
+    /// runs out, the list is returned as connman has it at that point. Fails
+    /// with `Error::SignalStreamClosed` if the signal stream ends, as the
+    /// connection is then gone.
+    pub async fn scan_services(
+        &self,
+        timeout: Duration,
+    ) -> Result<Vec<Service<Arc<SyncConnection>>>, ApiError> {
+        let manager = Manager::new(self.proxy.connection.clone(), self.proxy.timeout)
+            .with_parse_mode(self.parse_mode)
+            .with_retry_policy(self.retry.clone());
+        let deadline = Instant::now() + timeout;
+
+        let mut events = manager.events().await?;
+        tokio::time::timeout(timeout, self.scan())
+            .await
+            .map_err(|_| ApiError::Timeout(Cow::Owned(self.path().to_string())))??;
+
+        // Settled once no ServicesChanged came in for SCAN_SETTLE_TIME.
+        let mut settled_at = Instant::now() + SCAN_SETTLE_TIME;
+        loop {
+            let until = settled_at.min(deadline);
+            let now = Instant::now();
+            if now >= until {
+                break;
+            }
+            match tokio::time::timeout(until - now, events.next()).await {
+                Ok(Some(ManagerEvent::ServicesChanged { .. })) => {
+                    settled_at = Instant::now() + SCAN_SETTLE_TIME;
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    return Err(ApiError::SignalStreamClosed(Cow::Owned(
+                        self.path().to_string(),
+                    )))
+                }
+                Err(_) => break,
+            }
+        }
+
+        let type_ = Cow::<'static, str>::from(self.props.type_.clone());
+        Ok(manager
+            .get_services()
+            .await?
+            .into_iter()
+            .filter(|service| {
+                service
+                    .props
+                    .type_
+                    .clone()
+                    .map(Cow::<'static, str>::from)
+                    .as_ref()
+                    == Some(&type_)
+            })
+            .collect())
     }
 }
 
 impl<T: NonblockReply, C: Deref<Target = T>> Technology<C> {
     pub async fn set_powered(&self, powered: bool) -> Result<(), ApiError> {
-        Ok(ITechnology::set_property(&self.proxy, PropertyKind::Powered.into(), powered).await?)
+        self.retry
+            .run(|| ITechnology::set_property(&self.proxy, PropertyKind::Powered.into(), powered))
+            .await
     }
 
     pub async fn get_powered(&self) -> Result<bool, ApiError> {
-        let a = ITechnology::get_properties(&self.proxy).await?;
+        let a = self
+            .retry
+            .run(|| ITechnology::get_properties(&self.proxy))
+            .await?;
         Ok(super::get_property::<bool>(
             &a,
             PropertyKind::Powered.into(),
@@ -77,7 +191,10 @@ impl<T: NonblockReply, C: Deref<Target = T>> Technology<C> {
     }
 
     pub async fn get_connected(&self) -> Result<bool, ApiError> {
-        let a = ITechnology::get_properties(&self.proxy).await?;
+        let a = self
+            .retry
+            .run(|| ITechnology::get_properties(&self.proxy))
+            .await?;
         Ok(super::get_property::<bool>(
             &a,
             PropertyKind::Connected.into(),
@@ -85,7 +202,10 @@ impl<T: NonblockReply, C: Deref<Target = T>> Technology<C> {
     }
 
     pub async fn get_name(&self) -> Result<String, ApiError> {
-        let a = ITechnology::get_properties(&self.proxy).await?;
+        let a = self
+            .retry
+            .run(|| ITechnology::get_properties(&self.proxy))
+            .await?;
         Ok(super::get_property_fromstr::<String>(
             &a,
             PropertyKind::Name.into(),
@@ -93,7 +213,10 @@ impl<T: NonblockReply, C: Deref<Target = T>> Technology<C> {
     }
 
     pub async fn get_type(&self) -> Result<Type, ApiError> {
-        let a = ITechnology::get_properties(&self.proxy).await?;
+        let a = self
+            .retry
+            .run(|| ITechnology::get_properties(&self.proxy))
+            .await?;
         Ok(super::get_property_fromstr::<Type>(
             &a,
             PropertyKind::Type.into(),
@@ -101,7 +224,7 @@ impl<T: NonblockReply, C: Deref<Target = T>> Technology<C> {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Properties {
     pub powered: bool,
     pub connected: bool,
@@ -127,7 +250,7 @@ impl Properties {
         let connected = bool::from_properties(&props, PropertyKind::Connected.into())?;
         let name = String::from_properties(&props, PropertyKind::Name.into())?;
         let type_ = Type::from_properties(&props, PropertyKind::Type.into())?;
-        let tethering = bool::from_properties(&props, PropertyKind::Connected.into())?;
+        let tethering = bool::from_properties(&props, PropertyKind::Tethering.into())?;
 
         let tethering_identifier: Option<String> =
             FromProperties::from_properties(&props, PropertyKind::TetheringIdentifier.into())?;
@@ -146,6 +269,32 @@ impl Properties {
     }
 }
 
+impl IntoProperties for Properties {
+    fn into_properties(self) -> RefArgMap {
+        let mut props = RefArgMap::new();
+        insert_property(&mut props, PropertyKind::Powered.into(), self.powered);
+        insert_property(&mut props, PropertyKind::Connected.into(), self.connected);
+        insert_property(&mut props, PropertyKind::Name.into(), self.name);
+        insert_property(
+            &mut props,
+            PropertyKind::Type.into(),
+            Cow::<'static, str>::from(self.type_).into_owned(),
+        );
+        insert_property(&mut props, PropertyKind::Tethering.into(), self.tethering);
+        insert_property_opt(
+            &mut props,
+            PropertyKind::TetheringIdentifier.into(),
+            self.tethering_identifier,
+        );
+        insert_property_opt(
+            &mut props,
+            PropertyKind::TetheringPassphrase.into(),
+            self.tethering_passphrase,
+        );
+        props
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum PropertyKind {
     Powered,
@@ -203,3 +352,42 @@ impl FromStr for Type {
         Ok(t)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn properties_round_trip() {
+        let props = Properties {
+            powered: true,
+            connected: false,
+            name: "WiFi".to_string(),
+            type_: Type::Wifi,
+            tethering: true,
+            tethering_identifier: Some("hotspot".to_string()),
+            tethering_passphrase: Some("secret123".to_string()),
+        };
+        assert_eq!(
+            Properties::try_from(props.clone().into_properties()),
+            Ok(props)
+        );
+    }
+
+    #[test]
+    fn properties_round_trip_unknown_type() {
+        let props = Properties {
+            powered: false,
+            connected: false,
+            name: "Cellular".to_string(),
+            type_: Type::Unknown("cellular".to_string()),
+            tethering: false,
+            tethering_identifier: None,
+            tethering_passphrase: None,
+        };
+        assert_eq!(
+            Properties::try_from(props.clone().into_properties()),
+            Ok(props)
+        );
+    }
+}
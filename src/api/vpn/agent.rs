@@ -0,0 +1,316 @@
+//! Server side of the connman-vpnd Agent interface (`net.connman.vpn.Agent`)
+
+use async_trait::async_trait;
+use dbus::arg::{RefArg, Variant};
+use dbus::nonblock::{Proxy, SyncConnection};
+use dbus::Message;
+use thiserror::Error;
+
+use crate::api::agent::InputField;
+use crate::api::export::{call_no_reply, error_reply, invalid_args, Exported};
+use crate::api::gen::vpn_manager::Manager as IManager;
+use crate::api::{Error as ApiError, FromProperties, PropertyError, RefArgMap};
+use std::sync::Arc;
+
+const INTERFACE: &str = "net.connman.vpn.Agent";
+
+/// Errors a `VpnAgent` can reply with, as defined by the VPN agent API.
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum VpnAgentError {
+    /// Ask connman-vpnd to retry connecting (`ReportError`)
+    #[error("Retry")]
+    Retry,
+    /// The user canceled the request
+    #[error("Canceled")]
+    Canceled,
+}
+
+impl From<VpnAgentError> for &'static str {
+    fn from(err: VpnAgentError) -> Self {
+        match err {
+            VpnAgentError::Retry => "net.connman.vpn.Agent.Error.Retry",
+            VpnAgentError::Canceled => "net.connman.vpn.Agent.Error.Canceled",
+        }
+    }
+}
+
+/// Agent that connman-vpnd calls back into for VPN credentials.
+///
+/// Every method has a default implementation that declines the request, so
+/// only the calls of interest need to be implemented.
+#[async_trait]
+pub trait VpnAgent: Send + Sync + 'static {
+    /// Called when connman-vpnd unregisters the agent, e.g. on shutdown.
+    async fn release(&self) {}
+
+    /// Called when connecting `connection` failed with `error`.
+    async fn report_error(
+        &self,
+        connection: dbus::Path<'static>,
+        error: String,
+    ) -> Result<(), VpnAgentError> {
+        Ok(())
+    }
+
+    /// Called when connecting `connection` requires credentials.
+    async fn request_input(
+        &self,
+        connection: dbus::Path<'static>,
+        request: VpnInputRequest,
+    ) -> Result<VpnInputResponse, VpnAgentError> {
+        Err(VpnAgentError::Canceled)
+    }
+
+    /// Called when connman-vpnd cancels a pending request.
+    async fn cancel(&self) {}
+}
+
+/// VPN agent exported on the connection and registered with the vpnd Manager.
+///
+/// The agent is unregistered when this is dropped; use `unregister` to wait
+/// for connman-vpnd to acknowledge it instead.
+pub struct VpnAgentRegistration {
+    path: dbus::Path<'static>,
+    manager: Option<Proxy<'static, Arc<SyncConnection>>>,
+    _exported: Exported,
+}
+
+impl VpnAgentRegistration {
+    pub(crate) async fn new<A: VpnAgent>(
+        manager: Proxy<'static, Arc<SyncConnection>>,
+        path: dbus::Path<'static>,
+        agent: A,
+    ) -> Result<Self, ApiError> {
+        let agent = Arc::new(agent);
+        let exported = Exported::new(&manager.connection, path.clone(), INTERFACE, move |msg| {
+            Box::pin(dispatch(agent.clone(), msg))
+        });
+
+        IManager::register_agent(&manager, path.clone()).await?;
+
+        Ok(VpnAgentRegistration {
+            path,
+            manager: Some(manager),
+            _exported: exported,
+        })
+    }
+
+    pub fn path(&self) -> &dbus::Path<'static> {
+        &self.path
+    }
+
+    pub async fn unregister(mut self) -> Result<(), ApiError> {
+        match self.manager.take() {
+            Some(manager) => Ok(IManager::unregister_agent(&manager, self.path.clone()).await?),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for VpnAgentRegistration {
+    fn drop(&mut self) {
+        if let Some(manager) = self.manager.take() {
+            call_no_reply(
+                &manager.connection,
+                "net.connman.vpn",
+                "/".into(),
+                "net.connman.vpn.Manager",
+                "UnregisterAgent",
+                (self.path.clone(),),
+            );
+        }
+    }
+}
+
+async fn dispatch<A: VpnAgent>(agent: Arc<A>, msg: Message) -> Message {
+    let member = msg.member().map(|m| m.to_string()).unwrap_or_default();
+    match member.as_str() {
+        "Release" => {
+            agent.release().await;
+            msg.method_return()
+        }
+        "ReportError" => match msg.read2::<dbus::Path, String>() {
+            Ok((connection, error)) => {
+                let connection = connection.into_static();
+                match agent.report_error(connection, error).await {
+                    Ok(()) => msg.method_return(),
+                    Err(e) => error_reply(&msg, e.clone().into(), &e.to_string()),
+                }
+            }
+            Err(_) => invalid_args(&msg),
+        },
+        "RequestInput" => {
+            let (connection, fields) = match msg.read2::<dbus::Path, RefArgMap>() {
+                Ok(args) => args,
+                Err(_) => return invalid_args(&msg),
+            };
+            let request = match VpnInputRequest::try_from(fields) {
+                Ok(request) => request,
+                Err(e) => {
+                    return error_reply(
+                        &msg,
+                        "org.freedesktop.DBus.Error.InvalidArgs",
+                        &e.to_string(),
+                    )
+                }
+            };
+            match agent.request_input(connection.into_static(), request).await {
+                Ok(response) => msg.method_return().append1(response.into_dict()),
+                Err(e) => error_reply(&msg, e.clone().into(), &e.to_string()),
+            }
+        }
+        "Cancel" => {
+            agent.cancel().await;
+            msg.method_return()
+        }
+        _ => error_reply(
+            &msg,
+            "org.freedesktop.DBus.Error.UnknownMethod",
+            &format!("Unknown method '{}'", member),
+        ),
+    }
+}
+
+/// Fields connman-vpnd asks for in `RequestInput`.
+///
+/// Only the fields present in the request are set.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VpnInputRequest {
+    /// Connection name; informational only
+    pub name: Option<InputField>,
+    /// VPN server; informational only
+    pub host: Option<InputField>,
+    /// Set if the previous credentials were rejected; informational only
+    pub auth_failure: Option<InputField>,
+    pub username: Option<InputField>,
+    pub password: Option<InputField>,
+    /// Whether connman-vpnd may store the given credentials
+    pub save_credentials: Option<InputField>,
+    /// Cookie from an OpenConnect web login, alternate to credentials
+    pub openconnect_cookie: Option<InputField>,
+    /// OpenConnect server certificate fingerprint
+    pub openconnect_server_cert: Option<InputField>,
+    /// OpenConnect host to connect to after login
+    pub openconnect_vpn_host: Option<InputField>,
+    /// Password of the OpenVPN private key
+    pub openvpn_private_key_password: Option<InputField>,
+    /// VPNC IPsec group secret
+    pub vpnc_ipsec_secret: Option<InputField>,
+}
+
+impl VpnInputRequest {
+    pub fn try_from(fields: RefArgMap) -> Result<Self, PropertyError> {
+        let field = |kind: VpnInputKind| -> Result<Option<InputField>, PropertyError> {
+            FromProperties::from_properties(&fields, kind.into())
+        };
+
+        Ok(VpnInputRequest {
+            name: field(VpnInputKind::Name)?,
+            host: field(VpnInputKind::Host)?,
+            auth_failure: field(VpnInputKind::AuthFailure)?,
+            username: field(VpnInputKind::Username)?,
+            password: field(VpnInputKind::Password)?,
+            save_credentials: field(VpnInputKind::SaveCredentials)?,
+            openconnect_cookie: field(VpnInputKind::OpenConnectCookie)?,
+            openconnect_server_cert: field(VpnInputKind::OpenConnectServerCert)?,
+            openconnect_vpn_host: field(VpnInputKind::OpenConnectVpnHost)?,
+            openvpn_private_key_password: field(VpnInputKind::OpenVpnPrivateKeyPassword)?,
+            vpnc_ipsec_secret: field(VpnInputKind::VpncIpsecSecret)?,
+        })
+    }
+}
+
+/// Values to reply to a `VpnInputRequest` with.
+///
+/// Unset fields are left out of the reply.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VpnInputResponse {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub save_credentials: Option<bool>,
+    pub openconnect_cookie: Option<String>,
+    pub openconnect_server_cert: Option<String>,
+    pub openconnect_vpn_host: Option<String>,
+    pub openvpn_private_key_password: Option<String>,
+    pub vpnc_ipsec_secret: Option<String>,
+}
+
+impl VpnInputResponse {
+    fn into_dict(self) -> RefArgMap {
+        let mut dict = RefArgMap::new();
+        let mut insert = |kind: VpnInputKind, value: Option<Box<dyn RefArg>>| {
+            if let Some(value) = value {
+                let key: &'static str = kind.into();
+                dict.insert(key.to_string(), Variant(value));
+            }
+        };
+
+        insert(
+            VpnInputKind::Username,
+            self.username.map(|v| Box::new(v) as _),
+        );
+        insert(
+            VpnInputKind::Password,
+            self.password.map(|v| Box::new(v) as _),
+        );
+        insert(
+            VpnInputKind::SaveCredentials,
+            self.save_credentials.map(|v| Box::new(v) as _),
+        );
+        insert(
+            VpnInputKind::OpenConnectCookie,
+            self.openconnect_cookie.map(|v| Box::new(v) as _),
+        );
+        insert(
+            VpnInputKind::OpenConnectServerCert,
+            self.openconnect_server_cert.map(|v| Box::new(v) as _),
+        );
+        insert(
+            VpnInputKind::OpenConnectVpnHost,
+            self.openconnect_vpn_host.map(|v| Box::new(v) as _),
+        );
+        insert(
+            VpnInputKind::OpenVpnPrivateKeyPassword,
+            self.openvpn_private_key_password.map(|v| Box::new(v) as _),
+        );
+        insert(
+            VpnInputKind::VpncIpsecSecret,
+            self.vpnc_ipsec_secret.map(|v| Box::new(v) as _),
+        );
+        dict
+    }
+}
+
+/// VPN input field names.
+#[derive(Copy, Clone, Debug)]
+pub enum VpnInputKind {
+    Name,
+    Host,
+    AuthFailure,
+    Username,
+    Password,
+    SaveCredentials,
+    OpenConnectCookie,
+    OpenConnectServerCert,
+    OpenConnectVpnHost,
+    OpenVpnPrivateKeyPassword,
+    VpncIpsecSecret,
+}
+
+impl From<VpnInputKind> for &'static str {
+    fn from(kind: VpnInputKind) -> Self {
+        match kind {
+            VpnInputKind::Name => "Name",
+            VpnInputKind::Host => "Host",
+            VpnInputKind::AuthFailure => "VpnAgent.AuthFailure",
+            VpnInputKind::Username => "Username",
+            VpnInputKind::Password => "Password",
+            VpnInputKind::SaveCredentials => "SaveCredentials",
+            VpnInputKind::OpenConnectCookie => "OpenConnect.Cookie",
+            VpnInputKind::OpenConnectServerCert => "OpenConnect.ServerCert",
+            VpnInputKind::OpenConnectVpnHost => "OpenConnect.VPNHost",
+            VpnInputKind::OpenVpnPrivateKeyPassword => "OpenVPN.PrivateKeyPassword",
+            VpnInputKind::VpncIpsecSecret => "VPNC.IPSec.Secret",
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use dbus::arg::{RefArg, Variant};
+use dbus::message::{MatchRule, MessageType, SignalArgs};
+use dbus::nonblock::{NonblockReply, Proxy, SyncConnection};
+
+#[cfg(feature = "introspection")]
+use xml::reader::EventReader;
+
+use crate::api::gen::vpn_connection::{Connection as IConnection, ConnectionPropertyChanged};
+use crate::api::provider::VpnType;
+use crate::api::retry::RetryPolicy;
+use crate::api::signal::SignalStream;
+use crate::api::{
+    get_property_argiter, Error as ApiError, FromProperties, PropertyError, RefArgMap,
+};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Futures-aware wrapper struct for connman-vpnd Connection object.
+#[derive(Clone)]
+pub struct Connection<C> {
+    proxy: Proxy<'static, C>,
+    pub props: Properties,
+}
+
+impl<C> Connection<C> {
+    pub fn new(
+        connection: C,
+        path: dbus::Path<'static>,
+        args: RefArgMap,
+        timeout: Duration,
+    ) -> Result<Self, ApiError> {
+        Ok(Self::try_new(connection, path, args, timeout)?)
+    }
+
+    pub(crate) fn try_new(
+        connection: C,
+        path: dbus::Path<'static>,
+        args: RefArgMap,
+        timeout: Duration,
+    ) -> Result<Self, PropertyError> {
+        let properties = Properties::try_from(args)?;
+
+        Ok(Connection {
+            proxy: Self::proxy(path, timeout, connection),
+            props: properties,
+        })
+    }
+
+    pub fn proxy(path: dbus::Path<'static>, timeout: Duration, conn: C) -> Proxy<'static, C> {
+        let proxy = Proxy::new("net.connman.vpn", path, timeout, conn);
+        proxy
+    }
+
+    pub fn path(&self) -> &dbus::Path<'static> {
+        &self.proxy.path
+    }
+}
+
+impl<T: NonblockReply, C: Deref<Target = T>> Connection<C> {
+    #[cfg(feature = "introspection")]
+    pub async fn introspect(&self) -> Result<EventReader<std::io::Cursor<Vec<u8>>>, ApiError> {
+        use crate::api::gen::vpn_connection::OrgFreedesktopDBusIntrospectable as Introspectable;
+
+        let s = Introspectable::introspect(&self.proxy).await?;
+        let rdr = std::io::Cursor::new(s.into_bytes());
+        Ok(EventReader::new(rdr))
+    }
+
+    pub async fn connect(&self) -> Result<(), ApiError> {
+        Ok(IConnection::connect(&self.proxy).await?)
+    }
+
+    pub async fn disconnect(&self) -> Result<(), ApiError> {
+        Ok(IConnection::disconnect(&self.proxy).await?)
+    }
+
+    pub async fn get_properties(&self) -> Result<Properties, ApiError> {
+        let a = IConnection::get_properties(&self.proxy).await?;
+        Ok(Properties::try_from(a)?)
+    }
+
+    /// Replace the routes to set up through the VPN.
+    pub async fn set_user_routes(&self, routes: Vec<Route>) -> Result<(), ApiError> {
+        let routes: Vec<_> = routes.into_iter().map(Route::into_dict).collect();
+        Ok(IConnection::set_property(&self.proxy, PropertyKind::UserRoutes.into(), routes).await?)
+    }
+
+    pub async fn clear_user_routes(&self) -> Result<(), ApiError> {
+        Ok(IConnection::clear_property(&self.proxy, PropertyKind::UserRoutes.into()).await?)
+    }
+}
+
+impl Connection<Arc<SyncConnection>> {
+    /// Subscribe to property changes of this connection.
+    ///
+    /// The underlying match rule is removed once the returned stream is dropped.
+    pub async fn events(&self) -> Result<SignalStream<PropertyChanged>, ApiError> {
+        let rule = MatchRule::new()
+            .with_type(MessageType::Signal)
+            .with_sender("net.connman.vpn")
+            .with_path(self.proxy.path.clone())
+            .with_interface(ConnectionPropertyChanged::INTERFACE)
+            .with_member(ConnectionPropertyChanged::NAME);
+
+        SignalStream::new(
+            self.proxy.connection.clone(),
+            rule,
+            &RetryPolicy::default(),
+            |msg| {
+                ConnectionPropertyChanged::from_message(&msg).map(|s| PropertyChanged {
+                    name: s.name,
+                    value: s.value,
+                })
+            },
+        )
+        .await
+    }
+}
+
+/// A single property change of a VPN connection.
+#[derive(Debug)]
+pub struct PropertyChanged {
+    pub name: String,
+    pub value: Variant<Box<dyn RefArg + 'static>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Properties {
+    pub state: State,
+    pub type_: VpnType,
+    pub name: String,
+    pub domain: String,
+    /// VPN server
+    pub host: String,
+    /// Set if the connection was provisioned by a config file, and can't be
+    /// changed over D-Bus
+    pub immutable: bool,
+    /// Index of the VPN network interface; only set once connected
+    pub index: Option<i32>,
+    pub ipv4: Option<Ipv4>,
+    pub ipv6: Option<Ipv6>,
+    pub nameservers: Vec<String>,
+    /// Routes requested by the user
+    pub user_routes: Vec<Route>,
+    /// Routes pushed by the VPN server
+    pub server_routes: Vec<Route>,
+}
+
+impl Properties {
+    pub fn try_from(props: RefArgMap) -> Result<Self, PropertyError> {
+        let state = State::from_properties(&props, PropertyKind::State.into())?;
+        let type_ = VpnType::from_properties(&props, PropertyKind::Type.into())?;
+        let name = String::from_properties(&props, PropertyKind::Name.into())?;
+        let domain = String::from_properties(&props, PropertyKind::Domain.into())?;
+        let host = String::from_properties(&props, PropertyKind::Host.into())?;
+        let immutable: Option<bool> =
+            FromProperties::from_properties(&props, PropertyKind::Immutable.into())?;
+        let index: Option<i32> =
+            FromProperties::from_properties(&props, PropertyKind::Index.into())?;
+        let ipv4: Option<Ipv4> =
+            FromProperties::from_properties(&props, PropertyKind::Ipv4.into())?;
+        let ipv6: Option<Ipv6> =
+            FromProperties::from_properties(&props, PropertyKind::Ipv6.into())?;
+        let nameservers: Option<Vec<String>> =
+            FromProperties::from_properties(&props, PropertyKind::Nameservers.into())?;
+        let user_routes: Option<Vec<Route>> =
+            FromProperties::from_properties(&props, PropertyKind::UserRoutes.into())?;
+        let server_routes: Option<Vec<Route>> =
+            FromProperties::from_properties(&props, PropertyKind::ServerRoutes.into())?;
+
+        Ok(Properties {
+            state,
+            type_,
+            name,
+            domain,
+            host,
+            immutable: immutable.unwrap_or(false),
+            index,
+            ipv4,
+            ipv6,
+            nameservers: nameservers.unwrap_or_default(),
+            user_routes: user_routes.unwrap_or_default(),
+            server_routes: server_routes.unwrap_or_default(),
+        })
+    }
+}
+
+/// VPN connection property fields.
+#[derive(Copy, Clone, Debug)]
+pub enum PropertyKind {
+    State,
+    Type,
+    Name,
+    Domain,
+    Host,
+    Immutable,
+    Index,
+    Ipv4,
+    Ipv6,
+    Nameservers,
+    UserRoutes,
+    ServerRoutes,
+}
+
+impl From<PropertyKind> for &'static str {
+    fn from(prop: PropertyKind) -> Self {
+        match prop {
+            PropertyKind::State => "State",
+            PropertyKind::Type => "Type",
+            PropertyKind::Name => "Name",
+            PropertyKind::Domain => "Domain",
+            PropertyKind::Host => "Host",
+            PropertyKind::Immutable => "Immutable",
+            PropertyKind::Index => "Index",
+            PropertyKind::Ipv4 => "IPv4",
+            PropertyKind::Ipv6 => "IPv6",
+            PropertyKind::Nameservers => "Nameservers",
+            PropertyKind::UserRoutes => "UserRoutes",
+            PropertyKind::ServerRoutes => "ServerRoutes",
+        }
+    }
+}
+
+impl FromStr for PropertyKind {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "State" => Ok(PropertyKind::State),
+            "Type" => Ok(PropertyKind::Type),
+            "Name" => Ok(PropertyKind::Name),
+            "Domain" => Ok(PropertyKind::Domain),
+            "Host" => Ok(PropertyKind::Host),
+            "Immutable" => Ok(PropertyKind::Immutable),
+            "Index" => Ok(PropertyKind::Index),
+            "IPv4" => Ok(PropertyKind::Ipv4),
+            "IPv6" => Ok(PropertyKind::Ipv6),
+            "Nameservers" => Ok(PropertyKind::Nameservers),
+            "UserRoutes" => Ok(PropertyKind::UserRoutes),
+            "ServerRoutes" => Ok(PropertyKind::ServerRoutes),
+            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+        }
+    }
+}
+
+/// VPN connection state, `from_str` maps the values given over d-bus by
+/// connman-vpnd.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum State {
+    Idle,
+    Failure,
+    Configuration,
+    Ready,
+    Disconnect,
+}
+
+impl FromStr for State {
+    type Err = PropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "idle" => Ok(State::Idle),
+            "failure" => Ok(State::Failure),
+            "configuration" => Ok(State::Configuration),
+            "ready" => Ok(State::Ready),
+            "disconnect" => Ok(State::Disconnect),
+            _ => Err(PropertyError::Cast(Cow::Owned(s.to_string()))),
+        }
+    }
+}
+
+impl FromProperties for State {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        crate::api::get_property_fromstr::<Self>(properties, prop_name)
+    }
+}
+
+impl FromProperties for VpnType {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        crate::api::get_property_fromstr::<Self>(properties, prop_name)
+    }
+}
+
+/// Collects a dict of string values, skipping any other values.
+fn string_dict(
+    properties: &RefArgMap,
+    prop_name: &'static str,
+) -> Result<HashMap<String, String>, PropertyError> {
+    let mut i = get_property_argiter(properties, prop_name)?;
+    let mut m = HashMap::new();
+    while let Some(key) = i.next().and_then(|k| k.as_str()) {
+        if let Some(val) = i.next().and_then(|v| v.as_str()) {
+            let _ = m.insert(key.to_string(), val.to_string());
+        }
+    }
+    Ok(m)
+}
+
+/// VPN Ipv4 structure
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ipv4 {
+    pub address: Option<String>,
+    pub netmask: Option<String>,
+    pub gateway: Option<String>,
+    /// Address of the VPN server end of the tunnel
+    pub peer: Option<String>,
+}
+
+pub enum Ipv4Kind {
+    Address,
+    Netmask,
+    Gateway,
+    Peer,
+}
+
+impl From<Ipv4Kind> for &'static str {
+    fn from(prop: Ipv4Kind) -> Self {
+        match prop {
+            Ipv4Kind::Address => "Address",
+            Ipv4Kind::Netmask => "Netmask",
+            Ipv4Kind::Gateway => "Gateway",
+            Ipv4Kind::Peer => "Peer",
+        }
+    }
+}
+
+impl FromProperties for Ipv4 {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        let mut m = string_dict(properties, prop_name)?;
+
+        Ok(Ipv4 {
+            address: m.remove(Ipv4Kind::Address.into()),
+            netmask: m.remove(Ipv4Kind::Netmask.into()),
+            gateway: m.remove(Ipv4Kind::Gateway.into()),
+            peer: m.remove(Ipv4Kind::Peer.into()),
+        })
+    }
+}
+
+/// VPN Ipv6 structure
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ipv6 {
+    pub address: Option<String>,
+    pub prefix_length: Option<u8>,
+    pub gateway: Option<String>,
+    /// Address of the VPN server end of the tunnel
+    pub peer: Option<String>,
+}
+
+pub enum Ipv6Kind {
+    Address,
+    PrefixLength,
+    Gateway,
+    Peer,
+}
+
+impl From<Ipv6Kind> for &'static str {
+    fn from(prop: Ipv6Kind) -> Self {
+        match prop {
+            Ipv6Kind::Address => "Address",
+            Ipv6Kind::PrefixLength => "PrefixLength",
+            Ipv6Kind::Gateway => "Gateway",
+            Ipv6Kind::Peer => "Peer",
+        }
+    }
+}
+
+impl FromProperties for Ipv6 {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        let mut i = get_property_argiter(properties, prop_name)?;
+        let mut m: HashMap<&str, String> = HashMap::new();
+        let mut prefix_length = None;
+        while let Some(key) = i.next().and_then(|k| k.as_str()) {
+            let val = i.next();
+            if key == <&str>::from(Ipv6Kind::PrefixLength) {
+                // Sent as a byte by connman-vpnd
+                prefix_length = val.and_then(|v| {
+                    v.as_u64()
+                        .map(|len| len as u8)
+                        .or_else(|| v.as_str().and_then(|len| len.parse::<u8>().ok()))
+                });
+            } else if let Some(val) = val.and_then(|v| v.as_str()) {
+                let _ = m.insert(key, val.to_string());
+            }
+        }
+
+        Ok(Ipv6 {
+            address: m.remove(Ipv6Kind::Address.into()),
+            prefix_length,
+            gateway: m.remove(Ipv6Kind::Gateway.into()),
+            peer: m.remove(Ipv6Kind::Peer.into()),
+        })
+    }
+}
+
+/// Route set up through a VPN connection.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Route {
+    pub protocol_family: ProtocolFamily,
+    pub network: String,
+    /// Netmask, or prefix length for Ipv6 routes
+    pub netmask: String,
+    pub gateway: Option<String>,
+}
+
+impl Route {
+    fn into_dict(self) -> HashMap<&'static str, Variant<Box<dyn RefArg>>> {
+        let mut dict: HashMap<&'static str, Variant<Box<dyn RefArg>>> = HashMap::new();
+        dict.insert(
+            RouteKind::ProtocolFamily.into(),
+            Variant(Box::new(i32::from(self.protocol_family))),
+        );
+        dict.insert(RouteKind::Network.into(), Variant(Box::new(self.network)));
+        dict.insert(RouteKind::Netmask.into(), Variant(Box::new(self.netmask)));
+        if let Some(gateway) = self.gateway {
+            dict.insert(RouteKind::Gateway.into(), Variant(Box::new(gateway)));
+        }
+        dict
+    }
+}
+
+pub enum RouteKind {
+    ProtocolFamily,
+    Network,
+    Netmask,
+    Gateway,
+}
+
+impl From<RouteKind> for &'static str {
+    fn from(prop: RouteKind) -> Self {
+        match prop {
+            RouteKind::ProtocolFamily => "ProtocolFamily",
+            RouteKind::Network => "Network",
+            RouteKind::Netmask => "Netmask",
+            RouteKind::Gateway => "Gateway",
+        }
+    }
+}
+
+impl FromProperties for Vec<Route> {
+    fn from_properties(
+        properties: &RefArgMap,
+        prop_name: &'static str,
+    ) -> Result<Self, PropertyError> {
+        let routes = get_property_argiter(properties, prop_name)?;
+        let cast = || PropertyError::Cast(Cow::Borrowed(prop_name));
+
+        routes
+            .map(|route| {
+                let mut i = route.as_iter().ok_or_else(cast)?;
+                let mut m: HashMap<&str, &dyn RefArg> = HashMap::new();
+                while let Some(key) = i.next().and_then(|k| k.as_str()) {
+                    if let Some(val) = i.next() {
+                        let _ = m.insert(key, val);
+                    }
+                }
+                let string = |kind: RouteKind| {
+                    m.get(<&str>::from(kind))
+                        .and_then(|v| v.as_str())
+                        .map(String::from)
+                };
+
+                let protocol_family = m
+                    .get(<&str>::from(RouteKind::ProtocolFamily))
+                    .and_then(|v| v.as_i64())
+                    .and_then(|v| ProtocolFamily::try_from(v).ok())
+                    .ok_or_else(cast)?;
+
+                Ok(Route {
+                    protocol_family,
+                    network: string(RouteKind::Network).ok_or_else(cast)?,
+                    netmask: string(RouteKind::Netmask).ok_or_else(cast)?,
+                    gateway: string(RouteKind::Gateway),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Address family of a route.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ProtocolFamily {
+    Ipv4,
+    Ipv6,
+}
+
+impl ProtocolFamily {
+    fn try_from(family: i64) -> Result<Self, PropertyError> {
+        match family {
+            4 => Ok(ProtocolFamily::Ipv4),
+            6 => Ok(ProtocolFamily::Ipv6),
+            _ => Err(PropertyError::Cast(Cow::Owned(family.to_string()))),
+        }
+    }
+}
+
+impl From<ProtocolFamily> for i32 {
+    fn from(family: ProtocolFamily) -> Self {
+        match family {
+            ProtocolFamily::Ipv4 => 4,
+            ProtocolFamily::Ipv6 => 6,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use dbus::message::{MatchRule, MessageType, SignalArgs};
+use dbus::nonblock::{NonblockReply, Proxy, SyncConnection};
+use dbus::Message;
+
+#[cfg(feature = "introspection")]
+use xml::reader::EventReader;
+
+use super::agent::{VpnAgent, VpnAgentRegistration};
+use super::connection::Connection;
+use crate::api::gen::vpn_connection::Connection as IConnection;
+use crate::api::gen::vpn_manager::{
+    Manager as IManager, ManagerConnectionAdded, ManagerConnectionRemoved,
+};
+use crate::api::provider::ProviderSettings;
+use crate::api::retry::RetryPolicy;
+use crate::api::signal::SignalStream;
+use crate::api::{Error, PropertyError, RefArgMap};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Futures-aware wrapper struct for the connman-vpnd Manager object.
+#[derive(Clone)]
+pub struct Manager<C> {
+    pub(crate) proxy: Proxy<'static, C>,
+    pub(crate) timeout: Duration,
+}
+
+impl<C> Manager<C> {
+    pub fn new(connection: C, timeout: Duration) -> Self {
+        Manager {
+            proxy: Self::proxy(timeout, connection),
+            timeout,
+        }
+    }
+
+    pub fn proxy(timeout: Duration, conn: C) -> Proxy<'static, C> {
+        let proxy = Proxy::new("net.connman.vpn", "/", timeout, conn);
+        proxy
+    }
+}
+
+impl<T: NonblockReply, C: Deref<Target = T> + Clone> Manager<C> {
+    /// Connections that fail to parse are left out; see
+    /// `get_connections_partial`.
+    pub async fn get_connections(&self) -> Result<Vec<Connection<C>>, Error> {
+        Ok(self
+            .get_connections_partial()
+            .await?
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect())
+    }
+
+    /// Like `get_connections`, but also returns the path and parse error of
+    /// each connection that couldn't be parsed, in connman-vpnd's order.
+    pub async fn get_connections_partial(
+        &self,
+    ) -> Result<Vec<Result<Connection<C>, (dbus::Path<'static>, PropertyError)>>, Error> {
+        let connclone = self.proxy.connection.clone();
+
+        let v = IManager::get_connections(&self.proxy).await?;
+        Ok(v.into_iter()
+            .map(|(path, args)| {
+                Connection::try_new(connclone.clone(), path.clone(), args, self.timeout)
+                    .map_err(|e| (path, e))
+            })
+            .collect())
+    }
+
+    /// Create a VPN connection without connecting it.
+    ///
+    /// If the new connection can't be read back, it is removed again before
+    /// the error is returned.
+    pub async fn create(&self, settings: ProviderSettings) -> Result<Connection<C>, Error> {
+        settings.validate()?;
+
+        let path = IManager::create(&self.proxy, settings.to_dict()).await?;
+        let proxy = Connection::proxy(path.clone(), self.timeout, self.proxy.connection.clone());
+        let connection = IConnection::get_properties(&proxy)
+            .await
+            .map_err(Error::from)
+            .and_then(|args| {
+                Connection::new(
+                    self.proxy.connection.clone(),
+                    path.clone(),
+                    args,
+                    self.timeout,
+                )
+            });
+        if connection.is_err() {
+            // Best effort; the original error is the one worth reporting.
+            let _ = IManager::remove(&self.proxy, path).await;
+        }
+        connection
+    }
+
+    pub async fn remove(&self, connection: &Connection<C>) -> Result<(), Error> {
+        Ok(IManager::remove(&self.proxy, connection.path().clone()).await?)
+    }
+}
+
+impl<T: NonblockReply, C: Deref<Target = T>> Manager<C> {
+    #[cfg(feature = "introspection")]
+    pub async fn introspect(&self) -> Result<EventReader<std::io::Cursor<Vec<u8>>>, Error> {
+        use crate::api::gen::vpn_manager::OrgFreedesktopDBusIntrospectable as Introspectable;
+
+        let s = Introspectable::introspect(&self.proxy).await?;
+        let rdr = std::io::Cursor::new(s.into_bytes());
+        Ok(EventReader::new(rdr))
+    }
+}
+
+impl Manager<Arc<SyncConnection>> {
+    /// Subscribe to the signals emitted by the vpnd Manager object.
+    ///
+    /// The underlying match rule is removed once the returned stream is dropped.
+    pub async fn events(&self) -> Result<SignalStream<ManagerEvent>, Error> {
+        let rule = MatchRule::new()
+            .with_type(MessageType::Signal)
+            .with_sender("net.connman.vpn")
+            .with_path("/")
+            .with_interface("net.connman.vpn.Manager");
+
+        SignalStream::new(
+            self.proxy.connection.clone(),
+            rule,
+            &RetryPolicy::default(),
+            |msg| ManagerEvent::from_message(&msg),
+        )
+        .await
+    }
+
+    /// Export `agent` at `path` and register it as the connman-vpnd Agent.
+    pub async fn register_agent<A: VpnAgent>(
+        &self,
+        path: dbus::Path<'static>,
+        agent: A,
+    ) -> Result<VpnAgentRegistration, Error> {
+        VpnAgentRegistration::new(self.proxy.clone(), path, agent).await
+    }
+}
+
+/// Signals emitted by the vpnd Manager object.
+#[derive(Debug)]
+pub enum ManagerEvent {
+    /// A connection was created, along with its properties
+    ConnectionAdded {
+        path: dbus::Path<'static>,
+        properties: RefArgMap,
+    },
+    /// A connection was removed
+    ConnectionRemoved { path: dbus::Path<'static> },
+}
+
+impl ManagerEvent {
+    fn from_message(msg: &Message) -> Option<Self> {
+        match &*msg.member()? {
+            ManagerConnectionAdded::NAME => {
+                ManagerConnectionAdded::from_message(msg).map(|s| ManagerEvent::ConnectionAdded {
+                    path: s.identifier,
+                    properties: s.properties,
+                })
+            }
+            ManagerConnectionRemoved::NAME => ManagerConnectionRemoved::from_message(msg)
+                .map(|s| ManagerEvent::ConnectionRemoved { path: s.identifier }),
+            _ => None,
+        }
+    }
+}
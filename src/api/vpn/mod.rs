@@ -0,0 +1,8 @@
+//! Bindings for the connman VPN daemon (`net.connman.vpn`)
+
+pub mod agent;
+pub mod connection;
+pub mod manager;
+
+pub use self::connection::Connection;
+pub use self::manager::Manager;
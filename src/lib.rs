@@ -37,10 +37,24 @@
 //!     }
 //! }
 //! ```
+//!
+//! ## Signals
+//!
+//! Signal streams such as `Manager::events()` and `ServiceList` each add a
+//! match rule to the connection. When several of them with overlapping rules
+//! are used at once, turn on signal match mode, so that dbus-rs hands every
+//! signal to all of them rather than only to the first:
+//!
+//! ```rust,ignore
+//! let (resource, conn) = connection::new_system_sync().unwrap();
+//! conn.set_signal_match_mode(true);
+//! ```
 
 #![allow(unused)]
 #![allow(clippy::redundant_field_names, clippy::let_and_return)]
 
 pub mod api;
 
-pub use crate::api::{manager::Manager, service::Service, technology::Technology};
+pub use crate::api::{
+    clock::Clock, manager::Manager, peer::Peer, service::Service, technology::Technology,
+};